@@ -1,27 +1,37 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct DeviceConfig {
     pub connection_type: String, // "serial" or "socket"
     pub serial_port: Option<String>,
     pub socket_address: Option<String>,
     pub baud_rate: u32,
+    /// 线路协议："binary"（默认）或 "json_lines"
+    pub protocol: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct WebServerConfig {
     pub host: String,
     pub port: u16,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct WebSocketConfig {
     pub host: String,
     pub port: u16,
+    /// 设置后要求握手时携带匹配的 bearer token（query string 的 `access_token` 或
+    /// `Authorization: Bearer <token>` 头），否则拒绝连接；未设置时保持开放访问
+    pub auth_token: Option<String>,
+    /// 每个客户端有界发送队列的深度；队列满时新的数据帧会被丢弃（drop-newest）而不是阻塞广播循环
+    pub client_channel_depth: usize,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct StorageConfig {
     /// 保存文件的根目录（启动时会确保存在）
     pub data_dir: String,
@@ -31,14 +41,75 @@ pub struct StorageConfig {
     pub default_ext: String,
     /// 根目录最大保留文件数（超过后删除较旧文件）
     pub max_files: usize,
+    /// 存储后端："local"（默认）或 "s3"
+    pub backend: String,
+    /// backend = "s3" 时必填
+    pub s3: Option<S3Config>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// S3 兼容对象存储的连接参数（MinIO / Ceph RGW / AWS S3 等）
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct S3Config {
+    /// 形如 "https://minio.example.com:9000"
+    pub endpoint: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// 对象 key 前缀（不含首尾 "/"），可为空
+    pub prefix: String,
+}
+
+/// 可选的错误/事件上报（Sentry 兼容 DSN）。`enabled = false` 或 `dsn` 未设置时整个子系统是空操作。
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ObservabilityConfig {
+    pub enabled: bool,
+    /// 上报目标地址，例如 Sentry 项目的 Store API endpoint
+    pub dsn: Option<String>,
+    /// 随事件一起上报的部署环境标签，如 "production" / "staging"
+    pub environment: String,
+    /// 事件采样率，取值范围 [0.0, 1.0]；1.0 表示全部上报
+    pub sample_rate: f64,
+}
+
+/// 可选的 MQTT 遥测桥接（设备事件 -> broker，以及 broker -> 设备命令）。
+/// `enabled = false` 时不会启动该子系统。
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub broker_host: String,
+    pub broker_port: u16,
+    /// 0 = AtMostOnce, 1 = AtLeastOnce（默认）, 2 = ExactlyOnce
+    pub qos: u8,
+    /// 主题命名空间：recorder/<device_id>/...
+    pub device_id: String,
+}
+
+/// 可选的共享内存 IPC 数据通路：配合外部的 `data-reader` 前端进程，控制通道走具名
+/// 管道收发编解码帧（序号缺口重传请求等，见 [`crate::ipc::IpcClient`]），实际 ADC
+/// 数据通过共享内存环形缓冲区交付（见 [`crate::ipc::IpcBridge`]）。
+/// `enabled = false` 时不会启动该子系统。
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct IpcConfig {
+    pub enabled: bool,
+    /// 控制通道具名管道路径（Windows 具名管道 / Unix FIFO）
+    pub pipe_name: String,
+    /// 帧编解码格式："json_lines"（默认）/ "length_prefixed" / "msgpack"
+    pub codec: String,
+    /// Windows 共享内存段名称（`SharedMemoryReader::new` 的 `name` 参数），仅 Windows 下生效
+    pub shared_memory_name: Option<String>,
+    /// Unix 握手 socket 路径，通过 `SCM_RIGHTS` 接收共享内存 fd，仅 Unix 下生效
+    pub handshake_socket: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct Config {
     pub device: DeviceConfig,
     pub web_server: WebServerConfig,
     pub websocket: WebSocketConfig,
     pub storage: StorageConfig,
+    pub observability: ObservabilityConfig,
+    pub mqtt: MqttConfig,
+    pub ipc: IpcConfig,
 }
 
 impl Default for Config {
@@ -49,6 +120,7 @@ impl Default for Config {
                 serial_port: Some("COM7".into()),
                 socket_address: Some("127.0.0.1:9001".into()),
                 baud_rate: 115200,
+                protocol: "binary".into(),
             },
             web_server: WebServerConfig {
                 host: "127.0.0.1".into(),
@@ -57,27 +129,68 @@ impl Default for Config {
             websocket: WebSocketConfig {
                 host: "127.0.0.1".into(),
                 port: 8081,
+                auth_token: None,
+                client_channel_depth: 256,
             },
             storage: StorageConfig {
                 data_dir: "./data".into(),
                 default_prefix: "wave".into(),
                 default_ext: ".bin".into(),
                 max_files: 200,
+                backend: "local".into(),
+                s3: None,
+            },
+            observability: ObservabilityConfig {
+                enabled: false,
+                dsn: None,
+                environment: "development".into(),
+                sample_rate: 1.0,
+            },
+            mqtt: MqttConfig {
+                enabled: false,
+                broker_host: "127.0.0.1".into(),
+                broker_port: 1883,
+                qos: 1,
+                device_id: "recorder-01".into(),
+            },
+            ipc: IpcConfig {
+                enabled: false,
+                pipe_name: "/tmp/data-reader.pipe".into(),
+                codec: "json_lines".into(),
+                shared_memory_name: None,
+                handshake_socket: None,
             },
         }
     }
 }
 
 impl Config {
-    /// 载入配置：默认值 + 环境变量覆盖
+    /// 载入配置：默认值 < 配置文件 < 环境变量覆盖
+    ///
+    /// 配置文件路径由 `CONFIG_FILE` 环境变量指定，未设置时默认查找当前目录下的
+    /// `config.toml`；文件不存在时直接跳过这一层，不算错误。文件存在但解析失败会
+    /// 通过 `anyhow::Result` 返回，不会被静默忽略。可用 [`Config::save`] 把当前
+    /// 生效配置落盘为一份可编辑的起始模板。
     ///
     /// 支持的环境变量：
-    /// - DEVICE_TYPE, SERIAL_PORT, SOCKET_ADDRESS, BAUD_RATE
+    /// - CONFIG_FILE
+    /// - DEVICE_TYPE, SERIAL_PORT, SOCKET_ADDRESS, BAUD_RATE, DEVICE_PROTOCOL
     /// - WEB_HOST, WEB_PORT
-    /// - WS_HOST, WS_PORT
+    /// - WS_HOST, WS_PORT, WS_AUTH_TOKEN, WS_CLIENT_CHANNEL_DEPTH
     /// - DATA_DIR, FILE_PREFIX, FILE_EXT, MAX_FILES
+    /// - OBSERVABILITY_DSN, OBSERVABILITY_ENABLED, OBSERVABILITY_ENVIRONMENT, OBSERVABILITY_SAMPLE_RATE
+    /// - MQTT_ENABLED, MQTT_BROKER_HOST, MQTT_BROKER_PORT, MQTT_QOS, MQTT_DEVICE_ID
+    /// - IPC_ENABLED, IPC_PIPE_NAME, IPC_CODEC, IPC_SHARED_MEMORY_NAME, IPC_HANDSHAKE_SOCKET
     pub fn load() -> Result<Self> {
-        let mut cfg = Self::default();
+        let file_path = std::env::var("CONFIG_FILE").unwrap_or_else(|_| "config.toml".to_string());
+        let mut cfg = if Path::new(&file_path).exists() {
+            let content = std::fs::read_to_string(&file_path)
+                .map_err(|e| anyhow!("failed to read config file {}: {}", file_path, e))?;
+            toml::from_str(&content)
+                .map_err(|e| anyhow!("failed to parse config file {}: {}", file_path, e))?
+        } else {
+            Self::default()
+        };
 
         // Device
         if let Ok(v) = std::env::var("DEVICE_TYPE") {
@@ -94,6 +207,9 @@ impl Config {
                 cfg.device.baud_rate = rate;
             }
         }
+        if let Ok(v) = std::env::var("DEVICE_PROTOCOL") {
+            cfg.device.protocol = v;
+        }
 
         // Web
         if let Ok(v) = std::env::var("WEB_HOST") {
@@ -114,6 +230,14 @@ impl Config {
                 cfg.websocket.port = p;
             }
         }
+        if let Ok(v) = std::env::var("WS_AUTH_TOKEN") {
+            cfg.websocket.auth_token = Some(v);
+        }
+        if let Ok(v) = std::env::var("WS_CLIENT_CHANNEL_DEPTH") {
+            if let Ok(depth) = v.parse::<usize>() {
+                cfg.websocket.client_channel_depth = depth;
+            }
+        }
 
         // Storage
         if let Ok(v) = std::env::var("DATA_DIR") {
@@ -131,7 +255,188 @@ impl Config {
                 cfg.storage.max_files = n;
             }
         }
+        if let Ok(v) = std::env::var("STORAGE_BACKEND") {
+            cfg.storage.backend = v;
+        }
+        if let Ok(endpoint) = std::env::var("S3_ENDPOINT") {
+            cfg.storage.s3 = Some(S3Config {
+                endpoint,
+                bucket: std::env::var("S3_BUCKET").unwrap_or_default(),
+                access_key: std::env::var("S3_ACCESS_KEY").unwrap_or_default(),
+                secret_key: std::env::var("S3_SECRET_KEY").unwrap_or_default(),
+                prefix: std::env::var("S3_PREFIX").unwrap_or_default(),
+            });
+        }
+
+        // Observability
+        if let Ok(v) = std::env::var("OBSERVABILITY_DSN") {
+            cfg.observability.dsn = Some(v);
+            cfg.observability.enabled = true;
+        }
+        if let Ok(v) = std::env::var("OBSERVABILITY_ENABLED") {
+            cfg.observability.enabled = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("OBSERVABILITY_ENVIRONMENT") {
+            cfg.observability.environment = v;
+        }
+        if let Ok(v) = std::env::var("OBSERVABILITY_SAMPLE_RATE") {
+            if let Ok(rate) = v.parse::<f64>() {
+                cfg.observability.sample_rate = rate.clamp(0.0, 1.0);
+            }
+        }
+
+        // MQTT
+        if let Ok(v) = std::env::var("MQTT_ENABLED") {
+            cfg.mqtt.enabled = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("MQTT_BROKER_HOST") {
+            cfg.mqtt.broker_host = v;
+        }
+        if let Ok(v) = std::env::var("MQTT_BROKER_PORT") {
+            if let Ok(p) = v.parse::<u16>() {
+                cfg.mqtt.broker_port = p;
+            }
+        }
+        if let Ok(v) = std::env::var("MQTT_QOS") {
+            if let Ok(q) = v.parse::<u8>() {
+                cfg.mqtt.qos = q;
+            }
+        }
+        if let Ok(v) = std::env::var("MQTT_DEVICE_ID") {
+            cfg.mqtt.device_id = v;
+        }
+
+        // IPC（共享内存数据通路）
+        if let Ok(v) = std::env::var("IPC_ENABLED") {
+            cfg.ipc.enabled = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("IPC_PIPE_NAME") {
+            cfg.ipc.pipe_name = v;
+        }
+        if let Ok(v) = std::env::var("IPC_CODEC") {
+            cfg.ipc.codec = v;
+        }
+        if let Ok(v) = std::env::var("IPC_SHARED_MEMORY_NAME") {
+            cfg.ipc.shared_memory_name = Some(v);
+        }
+        if let Ok(v) = std::env::var("IPC_HANDSHAKE_SOCKET") {
+            cfg.ipc.handshake_socket = Some(v);
+        }
 
         Ok(cfg)
     }
+
+    /// 把当前生效配置序列化为 TOML 并写入 `path`，供操作员导出运行中的配置作为
+    /// 后续 `CONFIG_FILE` 的起始模板
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let toml_str = toml::to_string_pretty(self)
+            .map_err(|e| anyhow!("failed to serialize config to TOML: {}", e))?;
+        std::fs::write(path, toml_str)?;
+        Ok(())
+    }
+}
+
+/// 部分配置更新（`PUT /api/config`）：未设置的字段保持不变
+#[derive(Debug, Default, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ConfigUpdate {
+    pub device: Option<DeviceConfigUpdate>,
+    pub storage: Option<StorageConfigUpdate>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeviceConfigUpdate {
+    pub connection_type: Option<String>,
+    pub serial_port: Option<String>,
+    pub socket_address: Option<String>,
+    pub baud_rate: Option<u32>,
+    pub protocol: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct StorageConfigUpdate {
+    pub data_dir: Option<String>,
+    pub default_prefix: Option<String>,
+    pub default_ext: Option<String>,
+    pub max_files: Option<usize>,
+    pub backend: Option<String>,
+    /// 提供时整体替换当前的 S3 目标配置
+    pub s3: Option<S3Config>,
+}
+
+/// 持有运行时可变配置的共享句柄。克隆后仍指向同一份底层数据。
+#[derive(Clone)]
+pub struct ConfigController {
+    inner: Arc<RwLock<Config>>,
+}
+
+impl ConfigController {
+    pub fn new(cfg: Config) -> Self {
+        Self { inner: Arc::new(RwLock::new(cfg)) }
+    }
+
+    /// 当前生效配置的快照
+    pub async fn snapshot(&self) -> Config {
+        self.inner.read().await.clone()
+    }
+
+    /// 校验并应用一次部分更新，返回更新后的快照。
+    /// 调用方（web_server）负责在 `storage.data_dir` 改变时重建 FileManager，
+    /// 以及在 `device` 字段改变时把新的 DeviceConfig 下发给设备管理器。
+    pub async fn apply_update(&self, update: ConfigUpdate) -> Result<Config> {
+        Self::validate(&update)?;
+
+        let mut guard = self.inner.write().await;
+        if let Some(s) = update.storage {
+            if let Some(dir) = s.data_dir { guard.storage.data_dir = dir; }
+            if let Some(p) = s.default_prefix { guard.storage.default_prefix = p; }
+            if let Some(e) = s.default_ext {
+                guard.storage.default_ext = if e.starts_with('.') { e } else { format!(".{e}") };
+            }
+            if let Some(n) = s.max_files { guard.storage.max_files = n; }
+            if let Some(b) = s.backend { guard.storage.backend = b; }
+            if s.s3.is_some() { guard.storage.s3 = s.s3; }
+        }
+        if let Some(d) = update.device {
+            if let Some(t) = d.connection_type { guard.device.connection_type = t; }
+            if d.serial_port.is_some() { guard.device.serial_port = d.serial_port; }
+            if d.socket_address.is_some() { guard.device.socket_address = d.socket_address; }
+            if let Some(b) = d.baud_rate { guard.device.baud_rate = b; }
+            if let Some(p) = d.protocol { guard.device.protocol = p; }
+        }
+        Ok(guard.clone())
+    }
+
+    fn validate(update: &ConfigUpdate) -> Result<()> {
+        if let Some(s) = &update.storage {
+            if let Some(0) = s.max_files {
+                return Err(anyhow::anyhow!("storage.max_files must be greater than 0"));
+            }
+            if let Some(dir) = &s.data_dir {
+                if dir.trim().is_empty() {
+                    return Err(anyhow::anyhow!("storage.data_dir must not be empty"));
+                }
+            }
+            if let Some(b) = &s.backend {
+                if b != "local" && b != "s3" {
+                    return Err(anyhow::anyhow!("storage.backend must be 'local' or 's3'"));
+                }
+                if b == "s3" && s.s3.is_none() {
+                    return Err(anyhow::anyhow!("storage.s3 must be set when storage.backend is 's3'"));
+                }
+            }
+        }
+        if let Some(d) = &update.device {
+            if let Some(t) = &d.connection_type {
+                if t != "serial" && t != "socket" {
+                    return Err(anyhow::anyhow!("device.connection_type must be 'serial' or 'socket'"));
+                }
+            }
+            if let Some(p) = &d.protocol {
+                if p != "binary" && p != "json_lines" {
+                    return Err(anyhow::anyhow!("device.protocol must be 'binary' or 'json_lines'"));
+                }
+            }
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
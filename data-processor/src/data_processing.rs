@@ -1,12 +1,214 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tracing::info;
+use std::path::Path;
+use tracing::{info, warn};
 
 use crate::device_communication::{DataPacket, DataType, TriggerEvent};
+use crate::ipc::{SequenceGapStats, SequenceTracker};
+
+/// sled 数据库/树的名称，统一放在一处便于核对
+const CATALOG_DIR: &str = "trigger_catalog";
+const TREE_BURSTS: &str = "bursts";
+const TREE_SUMMARIES: &str = "summaries";
+const TREE_BY_TIME: &str = "by_time";
+
+/// 二进制批次格式的魔数/版本号，配合 [`Encoder`]/[`Decoder`] 使用，
+/// 详见 `export_burst_as_binary`/`import_trigger_burst`
+const BINARY_FORMAT_MAGIC: [u8; 4] = *b"TBB1";
+const BINARY_FORMAT_VERSION: u8 = 1;
+
+/// 面向 `Vec<u8>` 的游标式写入器：定长整数 + varint 长度前缀的字节块，
+/// 是 `export_burst_as_binary` 自描述二进制格式的底层编码原语
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// 以小端写入一个占 `n_bytes` 字节的定长无符号整数（`v` 的高位部分被截断）
+    fn encode_uint(&mut self, n_bytes: usize, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes()[..n_bytes]);
+    }
+
+    fn encode_u32(&mut self, v: u32) {
+        self.encode_uint(4, v as u64);
+    }
+
+    /// 写入一个 varint 长度前缀，再写入 `bytes` 本身
+    fn encode_vvec(&mut self, bytes: &[u8]) {
+        self.encode_varint(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn encode_varint(&mut self, mut v: u64) {
+        loop {
+            let byte = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                self.buf.push(byte);
+                break;
+            }
+            self.buf.push(byte | 0x80);
+        }
+    }
+
+    fn into_vec(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// [`Encoder`] 的读取侧：对 `&[u8]` 的只读游标，越界时返回错误而不是 panic
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    #[allow(dead_code)]
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    #[allow(dead_code)]
+    fn skip(&mut self, n: usize) -> Result<()> {
+        if n > self.remaining() {
+            return Err(anyhow::anyhow!("Truncated binary burst data"));
+        }
+        self.pos += n;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.remaining() {
+            return Err(anyhow::anyhow!("Truncated binary burst data"));
+        }
+        let slice = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    /// 读取一个占 `n_bytes` 字节的小端定长无符号整数
+    fn decode_uint(&mut self, n_bytes: usize) -> Result<u64> {
+        let bytes = self.read_bytes(n_bytes)?;
+        let mut padded = [0u8; 8];
+        padded[..n_bytes].copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(padded))
+    }
+
+    fn decode_u32(&mut self) -> Result<u32> {
+        Ok(self.decode_uint(4)? as u32)
+    }
+
+    fn decode_varint(&mut self) -> Result<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *self.read_bytes(1)?.first().unwrap();
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift >= 64 {
+                return Err(anyhow::anyhow!("Varint too long in binary burst data"));
+            }
+        }
+        Ok(result)
+    }
+
+    /// 读取一个 varint 长度前缀，再切出对应长度的字节切片
+    fn decode_vvec(&mut self) -> Result<&'a [u8]> {
+        let len = self.decode_varint()? as usize;
+        self.read_bytes(len)
+    }
+}
+
+/// 标准 CRC-32（IEEE 802.3，反射输入/输出，多项式 0xEDB88320），供 `encode_frame`/`decode_frames`
+/// 的帧完整性校验使用；逐位计算而非查表，与 `device_communication.rs` 里 `crc16` 的写法保持一致
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xEDB8_8320;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    !crc
+}
+
+/// Consistent Overhead Byte Stuffing：把 `data` 编码成不含内部 `0x00` 的帧，
+/// 并以单个 `0x00` 结尾作为帧定界符。沿着输入维护一个“code”字节，
+/// 它等于距离下一个零字节（或凑满 254 个非零字节）之前非零字节数加一；
+/// 每当遇到零字节或凑满 254 个非零字节，就把 code 和这段非零字节写出并重新开始
+fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_pos = 0;
+    output.push(0); // 占位，稍后回填
+    let mut code: u8 = 1;
+
+    for &byte in data {
+        if byte == 0 {
+            output[code_pos] = code;
+            code_pos = output.len();
+            output.push(0);
+            code = 1;
+        } else {
+            output.push(byte);
+            code += 1;
+            if code == 0xFF {
+                output[code_pos] = code;
+                code_pos = output.len();
+                output.push(0);
+                code = 1;
+            }
+        }
+    }
+    output[code_pos] = code;
+    output.push(0); // 帧定界符
+    output
+}
+
+/// [`cobs_encode`] 的逆操作，`frame` 不包含末尾的 `0x00` 定界符（调用方已按它切分）
+fn cobs_decode(frame: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(frame.len());
+    let mut i = 0;
+    while i < frame.len() {
+        let code = frame[i] as usize;
+        if code == 0 {
+            return Err(anyhow::anyhow!("Invalid COBS frame: zero code byte"));
+        }
+        let run_start = i + 1;
+        let run_end = run_start + (code - 1);
+        if run_end > frame.len() {
+            return Err(anyhow::anyhow!("Invalid COBS frame: truncated run"));
+        }
+        output.extend_from_slice(&frame[run_start..run_end]);
+        i = run_end;
+        if code != 0xFF && i < frame.len() {
+            output.push(0);
+        }
+    }
+    Ok(output)
+}
 
 /// 处理后的数据（供 WebSocket/文件保存使用）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ProcessedData {
     pub timestamp: u64,
     pub sequence: u64,
@@ -17,26 +219,26 @@ pub struct ProcessedData {
     pub data_type: ProcessedDataType,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ProcessedDataType {
     pub source: DataSource,
     pub trigger_info: Option<TriggerInfo>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub enum DataSource {
     Continuous,
     Trigger,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct TriggerInfo {
     pub trigger_timestamp: u32,
     pub is_complete: bool,
     pub sequence_in_burst: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct DataMetadata {
     pub packet_count: u64,
     pub processing_time_us: u64,
@@ -44,7 +246,7 @@ pub struct DataMetadata {
     pub channel_info: Vec<ChannelMetadata>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ChannelMetadata {
     pub channel_id: u8,
     pub sample_count: usize,
@@ -53,7 +255,7 @@ pub struct ChannelMetadata {
     pub avg_value: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(tag = "status", content = "message")]
 pub enum DataQuality {
     Good,
@@ -62,7 +264,7 @@ pub enum DataQuality {
 }
 
 /// 触发批次数据结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct TriggerBurst {
     pub burst_id: String,
     pub trigger_timestamp: u32,
@@ -76,7 +278,7 @@ pub struct TriggerBurst {
     pub quality_summary: DataQualitySummary,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct DataQualitySummary {
     pub overall_quality: DataQuality,
     pub channel_stats: Vec<ChannelStats>,
@@ -84,7 +286,7 @@ pub struct DataQualitySummary {
     pub anomaly_count: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ChannelStats {
     pub channel_id: u8,
     pub sample_count: usize,
@@ -94,7 +296,7 @@ pub struct ChannelStats {
     pub rms_value: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct TriggerSummary {
     pub burst_id: String,
     pub trigger_timestamp: u32,
@@ -106,41 +308,290 @@ pub struct TriggerSummary {
     pub can_save: bool,
 }
 
+/// `/api/v2/trigger/list` 的分页与筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct TriggerListFilter {
+    pub limit: usize,
+    pub offset: usize,
+    pub from_ts: Option<i64>,
+    pub to_ts: Option<i64>,
+    pub trigger_channel: Option<u16>,
+    pub quality: Option<String>,
+}
+
+/// 一页触发批次摘要，`total` 是筛选条件下的总数（不含分页），供前端计算页数
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TriggerListPage {
+    pub items: Vec<TriggerSummary>,
+    pub total: usize,
+}
+
+/// 预触发环形缓冲区覆盖的时间窗口（毫秒）
+const PRE_TRIGGER_WINDOW_MS: u64 = 5_000;
+/// 与 `estimate_sample_rate` 里假设的数据包间隔保持一致，用来把时间窗口换算成包数容量
+const ASSUMED_PACKET_INTERVAL_MS: u64 = 10;
+/// 预触发环形缓冲区的固定容量（包数），写满后覆盖最旧的条目
+const PRE_TRIGGER_RING_CAPACITY: usize = (PRE_TRIGGER_WINDOW_MS / ASSUMED_PACKET_INTERVAL_MS) as usize;
+
+/// `process_packets` 批次大小分布的分桶上界（最后一格之外的溢出桶不在此列出）
+const BATCH_SIZE_BUCKET_BOUNDS: [usize; 7] = [1, 2, 4, 8, 16, 32, 64];
+
+/// 单生产者环形缓冲区：保存最近一段时间内的连续采集数据包，供 `start_trigger_burst`
+/// 用最近 `pre_samples` 个样本回填批次的前导部分。固定容量、写满后覆盖最旧条目，
+/// 热路径（`push`）不分配内存
+struct PreTriggerRingBuffer {
+    entries: Vec<Option<ProcessedData>>,
+    capacity: usize,
+    /// 下一次写入的位置
+    head: usize,
+    len: usize,
+}
+
+impl PreTriggerRingBuffer {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: vec![None; capacity],
+            capacity,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, packet: ProcessedData) {
+        self.entries[self.head] = Some(packet);
+        self.head = (self.head + 1) % self.capacity;
+        self.len = (self.len + 1).min(self.capacity);
+    }
+
+    /// 从最新到最旧遍历，收集足以覆盖 `sample_count`（每通道样本数）的最近数据包，
+    /// 再按原始时间顺序（旧到新）返回
+    fn drain_recent(&self, sample_count: u32) -> Vec<ProcessedData> {
+        let mut collected = Vec::new();
+        let mut have = 0u32;
+        let mut idx = (self.head + self.capacity - 1) % self.capacity;
+
+        for _ in 0..self.len {
+            if have >= sample_count {
+                break;
+            }
+            if let Some(packet) = &self.entries[idx] {
+                let per_channel_samples = if packet.channel_count > 0 {
+                    (packet.data.len() / packet.channel_count) as u32
+                } else {
+                    0
+                };
+                have += per_channel_samples;
+                collected.push(packet.clone());
+            }
+            idx = (idx + self.capacity - 1) % self.capacity;
+        }
+
+        collected.reverse();
+        collected
+    }
+
+    fn fill_level(&self) -> usize {
+        self.len
+    }
+}
+
 pub struct DataProcessor {
     packet_sequence: u64,
     trigger_burst_sequence: u32,
     current_trigger_timestamp: Option<u32>,
-    
+
     // 触发批次管理
     current_trigger_burst: Option<TriggerBurst>,
-    completed_trigger_bursts: HashMap<String, TriggerBurst>,
-    max_cached_bursts: usize,
+    // 最近访问批次的内存缓存，避免每次预览/保存都重新反序列化完整批次；
+    // 完整目录持久化在下面的 sled 树中，重启后可懒加载重建
+    // 真正的 LRU：`get`/`push` 会把条目移到队首，超出容量时 O(1) 淘汰队尾（最久未使用）的条目，
+    // 不再需要每次插入都重新收集+排序全部条目
+    completed_trigger_bursts: lru::LruCache<String, TriggerBurst>,
+    cache_hits: u64,
+    cache_misses: u64,
+    // 最近一次被 LRU 淘汰出缓存的批次 id（批次仍留在 sled 目录中，只是需要重新懒加载）
+    last_evicted_burst_id: Option<String>,
+
+    // 持久化目录：bursts 存完整 TriggerBurst，summaries 存轻量 TriggerSummary 便于快速列表，
+    // by_time 是按 created_at 排序的二级索引（key = created_at 大端字节 + burst_id）
+    db: sled::Db,
+    bursts_tree: sled::Tree,
+    summaries_tree: sled::Tree,
+    by_time_tree: sled::Tree,
+
+    // decode_frames() 里 CRC-32 校验失败（或 COBS 解码失败）而被丢弃的帧数
+    cobs_frame_errors: u64,
+
+    // 最近一段连续数据的环形历史，供 start_trigger_burst 回填 pre_samples
+    pre_trigger_ring: PreTriggerRingBuffer,
+
+    // process_packets() 批次大小分布，按 BATCH_SIZE_BUCKET_BOUNDS 分桶计数，最后一格是溢出桶
+    batch_size_buckets: [u64; BATCH_SIZE_BUCKET_BOUNDS.len() + 1],
+
+    // 跟踪 DeviceEvent::DataPacket 携带的扩展序号（由 RawFrame::sequence 展开到 u16），检测设备
+    // 丢包/乱序；复用 ipc.rs 共享内存读取路径用的同一套序号跟踪算法
+    sequence_tracker: SequenceTracker<()>,
 }
 
 impl DataProcessor {
-    pub fn new() -> Self { 
-        Self {
+    /// 打开（或创建）位于 `data_dir/trigger_catalog` 的 sled 数据库，
+    /// 用来持久化触发批次目录，使其在进程重启后依然可查询
+    pub fn new(data_dir: &Path) -> Result<Self> {
+        let db = sled::open(data_dir.join(CATALOG_DIR))?;
+        let bursts_tree = db.open_tree(TREE_BURSTS)?;
+        let summaries_tree = db.open_tree(TREE_SUMMARIES)?;
+        let by_time_tree = db.open_tree(TREE_BY_TIME)?;
+
+        Ok(Self {
             packet_sequence: 0,
             trigger_burst_sequence: 0,
             current_trigger_timestamp: None,
             current_trigger_burst: None,
-            completed_trigger_bursts: HashMap::new(),
-            max_cached_bursts: 10,
+            completed_trigger_bursts: lru::LruCache::new(std::num::NonZeroUsize::new(10).unwrap()),
+            cache_hits: 0,
+            cache_misses: 0,
+            last_evicted_burst_id: None,
+            db,
+            bursts_tree,
+            summaries_tree,
+            by_time_tree,
+            cobs_frame_errors: 0,
+            pre_trigger_ring: PreTriggerRingBuffer::new(PRE_TRIGGER_RING_CAPACITY),
+            batch_size_buckets: [0; BATCH_SIZE_BUCKET_BOUNDS.len() + 1],
+            sequence_tracker: SequenceTracker::new(),
+        })
+    }
+
+    /// `by_time` 索引的 key：created_at 大端字节（保证按时间排序）+ burst_id
+    fn time_index_key(created_at: i64, burst_id: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(8 + burst_id.len());
+        // 加上偏移量把 i64 映射到无符号的字典序，负的 created_at 理论上不会出现，但保持稳妥
+        key.extend_from_slice(&(created_at as u64 ^ (1u64 << 63)).to_be_bytes());
+        key.extend_from_slice(burst_id.as_bytes());
+        key
+    }
+
+    fn summary_of(burst: &TriggerBurst, duration_ms: f64) -> TriggerSummary {
+        TriggerSummary {
+            burst_id: burst.burst_id.clone(),
+            trigger_timestamp: burst.trigger_timestamp,
+            trigger_channel: burst.trigger_channel,
+            total_samples: burst.total_samples,
+            duration_ms,
+            created_at: burst.created_at,
+            quality: match burst.quality_summary.overall_quality {
+                DataQuality::Good => "Good".to_string(),
+                DataQuality::Warning(_) => "Warning".to_string(),
+                DataQuality::Error(_) => "Error".to_string(),
+            },
+            can_save: burst.is_complete && !burst.data_packets.is_empty(),
         }
     }
 
+    /// 将完成的批次写入 sled（完整数据 + 摘要 + 时间索引）
+    fn persist_trigger_burst(&self, burst: &TriggerBurst, summary: &TriggerSummary) -> Result<()> {
+        let burst_bytes = serde_json::to_vec(burst)?;
+        let summary_bytes = serde_json::to_vec(summary)?;
+        self.bursts_tree.insert(burst.burst_id.as_bytes(), burst_bytes)?;
+        self.summaries_tree.insert(burst.burst_id.as_bytes(), summary_bytes)?;
+        self.by_time_tree.insert(Self::time_index_key(burst.created_at, &burst.burst_id), burst.burst_id.as_bytes())?;
+        Ok(())
+    }
+
     pub async fn run(&mut self) -> Result<()> { Ok(()) }
 
     /// 将设备上报的数据包转换为可视化友好的结构
     /// 移除了冗余的单位转换和信号处理，专注于数据组织和批次管理
     pub fn process_packet(&mut self, packet: &DataPacket) -> Result<ProcessedData> {
         let start_time = std::time::Instant::now();
+        let mut channel_scratch = Vec::new();
+        let mut result = self.process_packet_core(packet, &mut channel_scratch);
+        if let Ok(processed) = &mut result {
+            processed.metadata.processing_time_us = start_time.elapsed().as_micros() as u64;
+        }
+        result
+    }
+
+    /// 批量处理一次读取/系统调用里到手的所有数据包：整个批次只取一次时间戳
+    /// （均摊到每个成功处理的包上），并复用同一块通道样本暂存区而不是每包重新分配；
+    /// 触发状态机（`current_trigger_timestamp`/`trigger_burst_sequence`/`current_trigger_burst`）
+    /// 按包顺序依次推进，因此一段触发序列可以跨越批次内的多个包。
+    /// 返回与输入等长的逐包结果，单个包解析失败（长度不匹配、零通道等）不会影响批次里的其它包
+    pub fn process_packets(&mut self, packets: &[DataPacket]) -> Vec<Result<ProcessedData>> {
+        let start_time = std::time::Instant::now();
+        let mut channel_scratch = Vec::new();
+        let mut results = Vec::with_capacity(packets.len());
+
+        for packet in packets {
+            results.push(self.process_packet_core(packet, &mut channel_scratch));
+        }
+
+        self.record_batch_size(packets.len());
+
+        if !packets.is_empty() {
+            let per_packet_us = (start_time.elapsed().as_micros() as u64) / packets.len() as u64;
+            for result in &mut results {
+                if let Ok(processed) = result {
+                    processed.metadata.processing_time_us = per_packet_us;
+                }
+            }
+        }
+
+        results
+    }
+
+    /// 把一次 `process_packets` 调用的批次大小计入分布，落在 `BATCH_SIZE_BUCKET_BOUNDS`
+    /// 之外的（更大的）批次计入最后一格的溢出桶
+    fn record_batch_size(&mut self, size: usize) {
+        if size == 0 {
+            return;
+        }
+        let idx = BATCH_SIZE_BUCKET_BOUNDS.iter().position(|&bound| size <= bound)
+            .unwrap_or(BATCH_SIZE_BUCKET_BOUNDS.len());
+        self.batch_size_buckets[idx] += 1;
+    }
+
+    /// 把内部分桶计数导出成适合对外暴露的 `(上界, 计数)` 列表，最后一项 `upper_bound` 为
+    /// `None` 表示 "大于最大分桶上界" 的溢出桶
+    fn batch_size_histogram(&self) -> Vec<BatchSizeBucket> {
+        BATCH_SIZE_BUCKET_BOUNDS.iter()
+            .map(|&bound| Some(bound))
+            .chain(std::iter::once(None))
+            .zip(self.batch_size_buckets.iter())
+            .map(|(upper_bound, &count)| BatchSizeBucket { upper_bound, count })
+            .collect()
+    }
+
+    /// 喂一个设备数据包的序号给 [`SequenceTracker`]，检测丢包/乱序；这里只做计数/观测，
+    /// 不缓冲实际数据做重排交付——`process_packet`/`process_packets` 按到达顺序同步处理
+    /// 每个包，没有可延迟投递的缓冲层，重排窗口里存的 payload 用 `()` 占位即可。
+    /// 命中真正的缺口时只记日志，这条路径没有类似 `IpcClient::send_json` 的控制通道可发
+    /// 重传请求。
+    fn track_packet_sequence(&mut self, sequence: u16) {
+        self.sequence_tracker.accept(sequence, (), |missing_from, missing_to| {
+            warn!(
+                "Detected device data packet sequence gap: missing {}..={}, packets may have been dropped",
+                missing_from, missing_to
+            );
+        });
+    }
+
+    /// 当前的设备数据包丢包/重传/重排统计，供状态接口展示
+    pub fn sequence_gap_stats(&self) -> SequenceGapStats {
+        self.sequence_tracker.stats()
+    }
+
+    /// `process_packet`/`process_packets` 共用的核心逻辑，不计时（由调用方统一处理），
+    /// `channel_scratch` 是调用方持有的可复用暂存区，用来容纳逐通道样本，避免每个通道都重新分配
+    fn process_packet_core(&mut self, packet: &DataPacket, channel_scratch: &mut Vec<f64>) -> Result<ProcessedData> {
         self.packet_sequence += 1;
+        self.track_packet_sequence(packet.sequence);
 
         // 1) 解析多通道数据（非交错格式）
         let channel_count = packet.enabled_channels.count_ones() as usize;
         let sample_count = packet.sample_count as usize;
-        
+
         if channel_count == 0 {
             return Err(anyhow::anyhow!("No enabled channels"));
         }
@@ -149,7 +600,7 @@ impl DataProcessor {
         let expected_len = channel_count * sample_count * 2; // int16 = 2 bytes
         if packet.sensor_data.len() != expected_len {
             return Err(anyhow::anyhow!(
-                "Data length mismatch: expected {}, got {}", 
+                "Data length mismatch: expected {}, got {}",
                 expected_len, packet.sensor_data.len()
             ));
         }
@@ -161,24 +612,25 @@ impl DataProcessor {
         for ch_idx in 0..channel_count {
             let start_idx = ch_idx * sample_count * 2;
             let end_idx = start_idx + sample_count * 2;
-            
+
             if end_idx <= packet.sensor_data.len() {
                 let ch_data = &packet.sensor_data[start_idx..end_idx];
-                let mut channel_samples = Vec::with_capacity(sample_count);
-                
+                channel_scratch.clear();
+                channel_scratch.reserve(sample_count);
+
                 for sample_bytes in ch_data.chunks_exact(2) {
                     let raw = i16::from_le_bytes([sample_bytes[0], sample_bytes[1]]);
                     // 直接使用设备提供的值，假设设备已完成单位转换
                     let value = raw as f64;
-                    channel_samples.push(value);
+                    channel_scratch.push(value);
                 }
 
                 // 计算通道统计信息（用于质量监控，但不修改数据）
-                let (min_val, max_val, sum) = channel_samples.iter().fold(
+                let (min_val, max_val, sum) = channel_scratch.iter().fold(
                     (f64::INFINITY, f64::NEG_INFINITY, 0.0),
                     |(min, max, sum), &val| (min.min(val), max.max(val), sum + val)
                 );
-                
+
                 let channel_id = self.get_channel_id_from_mask(packet.enabled_channels, ch_idx as u8);
                 channel_metadata.push(ChannelMetadata {
                     channel_id,
@@ -188,7 +640,7 @@ impl DataProcessor {
                     avg_value: if sample_count > 0 { sum / sample_count as f64 } else { 0.0 },
                 });
 
-                all_samples.extend(channel_samples);
+                all_samples.extend_from_slice(channel_scratch);
             }
         }
 
@@ -211,15 +663,15 @@ impl DataProcessor {
                     self.current_trigger_timestamp = Some(*trigger_timestamp);
                     self.trigger_burst_sequence = 0;
                 }
-                
+
                 self.trigger_burst_sequence += 1;
-                
+
                 let trigger_info = TriggerInfo {
                     trigger_timestamp: *trigger_timestamp,
                     is_complete: *is_complete,
                     sequence_in_burst: Some(self.trigger_burst_sequence),
                 };
-                
+
                 ProcessedDataType {
                     source: DataSource::Trigger,
                     trigger_info: Some(trigger_info),
@@ -227,8 +679,6 @@ impl DataProcessor {
             }
         };
 
-        let processing_time = start_time.elapsed().as_micros() as u64;
-
         let processed = ProcessedData {
             timestamp: packet.timestamp_ms as u64,
             sequence: self.packet_sequence,
@@ -237,39 +687,49 @@ impl DataProcessor {
             data: all_samples, // 使用原始数据，不进行滤波
             metadata: DataMetadata {
                 packet_count: self.packet_sequence,
-                processing_time_us: processing_time,
+                processing_time_us: 0, // 由 process_packet/process_packets 统一回填
                 data_quality: quality,
                 channel_info: channel_metadata,
             },
             data_type,
         };
 
-        // 如果是触发数据，添加到当前批次
-        if let DataType::Trigger { .. } = &packet.data_type {
-            if let Some(ref mut burst) = self.current_trigger_burst {
-                burst.data_packets.push(processed.clone());
-                burst.total_samples += processed.data.len();
+        // 如果是触发数据，添加到当前批次；连续数据则写入预触发环形缓冲区，
+        // 这样下一次触发时 start_trigger_burst 才有历史可回填
+        match &packet.data_type {
+            DataType::Trigger { .. } => {
+                if let Some(ref mut burst) = self.current_trigger_burst {
+                    burst.data_packets.push(processed.clone());
+                    burst.total_samples += processed.data.len();
+                }
+            }
+            DataType::Continuous => {
+                self.pre_trigger_ring.push(processed.clone());
             }
         }
 
         Ok(processed)
     }
 
-    /// 开始新的触发批次
+    /// 开始新的触发批次：先从预触发环形缓冲区回填最近 `pre_samples` 个样本，
+    /// 让导出的批次包含触发前的窗口，而不仅仅是触发后采集到的数据
     pub fn start_trigger_burst(&mut self, trigger_event: &TriggerEvent) -> String {
-        let burst_id = format!("trigger_{}_{}", 
-                              trigger_event.timestamp, 
+        let burst_id = format!("trigger_{}_{}",
+                              trigger_event.timestamp,
                               chrono::Utc::now().timestamp_millis());
-        
+
+        let pre_trigger_packets = self.pre_trigger_ring.drain_recent(trigger_event.pre_samples);
+        let total_samples = pre_trigger_packets.iter().map(|p| p.data.len()).sum();
+
         self.current_trigger_burst = Some(TriggerBurst {
             burst_id: burst_id.clone(),
             trigger_timestamp: trigger_event.timestamp,
             trigger_channel: trigger_event.channel,
             pre_samples: trigger_event.pre_samples,
             post_samples: trigger_event.post_samples,
-            data_packets: Vec::new(),
+            data_packets: pre_trigger_packets,
             is_complete: false,
-            total_samples: 0,
+            total_samples,
             created_at: chrono::Utc::now().timestamp_millis(),
             quality_summary: DataQualitySummary {
                 overall_quality: DataQuality::Good,
@@ -278,36 +738,30 @@ impl DataProcessor {
                 anomaly_count: 0,
             },
         });
-        
+
         info!("Started new trigger burst: {}", burst_id);
         burst_id
     }
 
-    /// 完成当前触发批次
+    /// 完成当前触发批次：计算质量摘要、写入 sled 持久化目录，并更新内存热缓存
     pub fn complete_trigger_burst(&mut self) -> Option<TriggerBurst> {
         if let Some(mut burst) = self.current_trigger_burst.take() {
             burst.is_complete = true;
-            
+
             // 计算质量摘要
             self.calculate_quality_summary(&mut burst);
-            
-            // 添加到完成列表
-            let burst_id = burst.burst_id.clone();
-            self.completed_trigger_bursts.insert(burst_id, burst.clone());
-            
-            // 限制缓存数量（保留最新的）
-            if self.completed_trigger_bursts.len() > self.max_cached_bursts {
-                let mut timestamps: Vec<_> = self.completed_trigger_bursts.values()
-                    .map(|b| (b.created_at, b.burst_id.clone()))
-                    .collect();
-                timestamps.sort_by_key(|&(ts, _)| ts);
-                
-                // 删除最旧的
-                let oldest_id = &timestamps[0].1;
-                self.completed_trigger_bursts.remove(oldest_id);
+
+            let summary = Self::summary_of(&burst, self.calculate_duration_ms(&burst));
+            if let Err(e) = self.persist_trigger_burst(&burst, &summary) {
+                warn!("Failed to persist trigger burst {} to catalog: {}", burst.burst_id, e);
             }
-            
-            info!("Completed trigger burst: {} with {} packets", 
+
+            // 添加到内存热缓存（LRU，容量超出时自动淘汰最久未使用的条目，
+            // 被逐出的批次仍然留在 sled 目录中，只是要重新懒加载）
+            let burst_id = burst.burst_id.clone();
+            self.insert_cached_burst(burst_id, burst.clone());
+
+            info!("Completed trigger burst: {} with {} packets",
                   burst.burst_id, burst.data_packets.len());
             Some(burst)
         } else {
@@ -315,55 +769,121 @@ impl DataProcessor {
         }
     }
 
-    /// 获取触发批次摘要列表
-    pub fn get_trigger_summaries(&self) -> Vec<TriggerSummary> {
-        let mut summaries: Vec<_> = self.completed_trigger_bursts.values()
-            .map(|burst| TriggerSummary {
-                burst_id: burst.burst_id.clone(),
-                trigger_timestamp: burst.trigger_timestamp,
-                trigger_channel: burst.trigger_channel,
-                total_samples: burst.total_samples,
-                duration_ms: self.calculate_duration_ms(burst),
-                created_at: burst.created_at,
-                quality: match burst.quality_summary.overall_quality {
-                    DataQuality::Good => "Good".to_string(),
-                    DataQuality::Warning(_) => "Warning".to_string(),
-                    DataQuality::Error(_) => "Error".to_string(),
-                },
-                can_save: burst.is_complete && !burst.data_packets.is_empty(),
-            })
-            .collect();
-        
-        // 按创建时间倒序排列
-        summaries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-        summaries
+    /// 按时间倒序分页列出触发批次摘要，支持按时间范围/通道/质量筛选；
+    /// 源数据来自 sled 的 `by_time` 索引与 `summaries` 树，不要求批次留在内存缓存中
+    pub fn list_trigger_summaries(&self, filter: &TriggerListFilter) -> Result<TriggerListPage> {
+        let mut matched = Vec::new();
+        let mut total = 0usize;
+
+        // by_time 按字典序（即时间正序）存储，倒序遍历即可拿到“最新优先”的结果
+        for entry in self.by_time_tree.iter().rev() {
+            let (_key, value) = entry?;
+            let burst_id = String::from_utf8_lossy(&value).into_owned();
+
+            let Some(raw) = self.summaries_tree.get(burst_id.as_bytes())? else {
+                continue;
+            };
+            let summary: TriggerSummary = serde_json::from_slice(&raw)?;
+
+            if let Some(from_ts) = filter.from_ts {
+                if summary.created_at < from_ts { continue; }
+            }
+            if let Some(to_ts) = filter.to_ts {
+                if summary.created_at > to_ts { continue; }
+            }
+            if let Some(channel) = filter.trigger_channel {
+                if summary.trigger_channel != channel { continue; }
+            }
+            if let Some(quality) = &filter.quality {
+                if &summary.quality != quality { continue; }
+            }
+
+            total += 1;
+            if total > filter.offset && matched.len() < filter.limit {
+                matched.push(summary);
+            }
+        }
+
+        Ok(TriggerListPage { items: matched, total })
+    }
+
+    /// 获取指定触发批次的详细数据：优先读内存热缓存（命中时顺带把它标记为最近使用），
+    /// 未命中时从 sled 懒加载并补充缓存
+    pub fn get_trigger_burst(&mut self, burst_id: &str) -> Option<TriggerBurst> {
+        // `get` 本身就会把命中的条目移到 LRU 队首，不需要额外的 touch 调用
+        if let Some(burst) = self.completed_trigger_bursts.get(burst_id) {
+            self.cache_hits += 1;
+            return Some(burst.clone());
+        }
+        self.cache_misses += 1;
+
+        let raw = self.bursts_tree.get(burst_id.as_bytes()).ok().flatten()?;
+        let burst: TriggerBurst = serde_json::from_slice(&raw).ok()?;
+
+        self.insert_cached_burst(burst_id.to_string(), burst.clone());
+
+        Some(burst)
     }
 
-    /// 获取指定触发批次的详细数据
-    pub fn get_trigger_burst(&self, burst_id: &str) -> Option<&TriggerBurst> {
-        self.completed_trigger_bursts.get(burst_id)
+    /// 把一个批次放入 LRU 缓存，容量超出时自动淘汰最久未使用的条目，
+    /// 并记下被淘汰批次的 id 供 [`ProcessingStats::last_evicted_burst_id`] 展示
+    fn insert_cached_burst(&mut self, burst_id: String, burst: TriggerBurst) {
+        if let Some((evicted_id, _)) = self.completed_trigger_bursts.push(burst_id.clone(), burst) {
+            if evicted_id != burst_id {
+                self.last_evicted_burst_id = Some(evicted_id);
+            }
+        }
     }
 
-    /// 删除指定的触发批次
+    /// 删除指定的触发批次：同时清理内存缓存、sled 目录与时间索引
     pub fn remove_trigger_burst(&mut self, burst_id: &str) -> bool {
-        self.completed_trigger_bursts.remove(burst_id).is_some()
+        self.completed_trigger_bursts.pop(burst_id);
+
+        let removed_summary = self.summaries_tree.remove(burst_id.as_bytes()).ok().flatten();
+        let removed_burst = self.bursts_tree.remove(burst_id.as_bytes()).ok().flatten();
+
+        if let Some(raw) = &removed_summary {
+            if let Ok(summary) = serde_json::from_slice::<TriggerSummary>(raw) {
+                let _ = self.by_time_tree.remove(Self::time_index_key(summary.created_at, burst_id));
+            }
+        }
+
+        removed_summary.is_some() || removed_burst.is_some()
     }
 
     /// 导出触发批次为保存格式
-    pub fn export_trigger_burst(&self, burst_id: &str, format: &str) -> Result<Vec<u8>> {
+    pub fn export_trigger_burst(&mut self, burst_id: &str, format: &str) -> Result<Vec<u8>> {
         let burst = self.get_trigger_burst(burst_id)
             .ok_or_else(|| anyhow::anyhow!("Trigger burst not found: {}", burst_id))?;
 
         match format {
             "json" => {
-                let json = serde_json::to_string_pretty(burst)?;
+                let json = serde_json::to_string_pretty(&burst)?;
                 Ok(json.into_bytes())
             }
             "csv" => {
-                self.export_burst_as_csv(burst)
+                self.export_burst_as_csv(&burst)
             }
             "binary" => {
-                self.export_burst_as_binary(burst)
+                self.export_burst_as_binary(&burst)
+            }
+            // 每个数据包各自一帧：CRC-32 校验 + COBS 成帧，帧内无 0x00、以单个 0x00 结尾，
+            // 适合串口等流式传输或追加写入的日志文件——即便中途断线丢了若干字节，
+            // 消费者也能靠 0x00 定界符重新对齐到下一帧，而不必依赖长度头
+            "cobs" => {
+                let mut out = Vec::new();
+                for packet in &burst.data_packets {
+                    out.extend(self.encode_frame(packet));
+                }
+                Ok(out)
+            }
+            // 自描述的紧凑二进制格式，下游分析管线可以直接读取，跳过 JSON 解析
+            "cbor" => {
+                Ok(serde_cbor::to_vec(&burst)?)
+            }
+            // 固定 schema 的最小体积编码，体积比 cbor 更小，但读写双方需对齐 TriggerBurst 定义
+            "bincode" => {
+                Ok(bincode::serialize(&burst)?)
             }
             _ => Err(anyhow::anyhow!("Unsupported format: {}", format))
         }
@@ -545,26 +1065,166 @@ impl DataProcessor {
         Ok(csv_content.into_bytes())
     }
 
+    /// 将单个 [`ProcessedData`] 的 timestamp/sequence/channel_count/sample_rate/samples
+    /// 写入 `enc`，是批次二进制格式与逐包 COBS 帧共用的编码逻辑
+    fn encode_packet_binary(enc: &mut Encoder, packet: &ProcessedData) {
+        enc.encode_uint(8, packet.timestamp);
+        enc.encode_uint(8, packet.sequence);
+        enc.encode_u32(packet.channel_count as u32);
+        enc.encode_uint(8, packet.sample_rate.to_bits());
+
+        let mut sample_bytes = Vec::with_capacity(packet.data.len() * 8);
+        for &sample in &packet.data {
+            sample_bytes.extend(&sample.to_le_bytes());
+        }
+        enc.encode_vvec(&sample_bytes);
+    }
+
+    /// 把一个数据包编码为 CRC-32 校验、COBS 成帧后的字节流：负载内部不含 `0x00`，
+    /// 并以单个 `0x00` 结尾作为帧定界符，适合串口等无长度头的流式传输，
+    /// 或直接追加到一个允许中途中断的日志文件里
+    pub fn encode_frame(&self, data: &ProcessedData) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        Self::encode_packet_binary(&mut enc, data);
+        let mut framed = enc.into_vec();
+        let crc = crc32(&framed);
+        framed.extend_from_slice(&crc.to_le_bytes());
+        cobs_encode(&framed)
+    }
+
+    /// [`Self::encode_frame`] 的逆操作：按 `0x00` 切分帧、逐帧做 COBS 解码并校验 CRC-32，
+    /// 返回通过校验的负载；校验失败的帧会被丢弃，并计入 `cobs_frame_errors`
+    /// （中途断线后重新接上流时，允许跳过损坏的那一帧而不必整体重新同步）
+    pub fn decode_frames(&mut self, stream: &[u8]) -> Vec<Vec<u8>> {
+        let mut payloads = Vec::new();
+        for chunk in stream.split(|&b| b == 0) {
+            if chunk.is_empty() {
+                continue;
+            }
+            let framed = match cobs_decode(chunk) {
+                Ok(framed) if framed.len() >= 4 => framed,
+                _ => {
+                    self.cobs_frame_errors += 1;
+                    continue;
+                }
+            };
+            let split_at = framed.len() - 4;
+            let (payload, crc_bytes) = framed.split_at(split_at);
+            let expected_crc = u32::from_le_bytes(crc_bytes.try_into().unwrap());
+            if crc32(payload) == expected_crc {
+                payloads.push(payload.to_vec());
+            } else {
+                self.cobs_frame_errors += 1;
+            }
+        }
+        payloads
+    }
+
     fn export_burst_as_binary(&self, burst: &TriggerBurst) -> Result<Vec<u8>> {
-        // 简单的二进制格式：
-        // [8字节头] [4字节样本数] [样本数据...]
-        let mut binary_data = Vec::new();
-        
-        // 写入头部信息
-        binary_data.extend(&burst.trigger_timestamp.to_le_bytes());
-        binary_data.extend(&(burst.trigger_channel as u32).to_le_bytes());
-        
-        // 写入样本数
-        binary_data.extend(&(burst.total_samples as u32).to_le_bytes());
-        
-        // 写入样本数据（32位浮点数）
+        let mut enc = Encoder::new();
+        enc.write_bytes(&BINARY_FORMAT_MAGIC);
+        enc.encode_uint(1, BINARY_FORMAT_VERSION as u64);
+
+        enc.encode_vvec(burst.burst_id.as_bytes());
+        enc.encode_u32(burst.trigger_timestamp);
+        enc.encode_uint(2, burst.trigger_channel as u64);
+        enc.encode_u32(burst.pre_samples);
+        enc.encode_u32(burst.post_samples);
+        enc.encode_uint(8, burst.created_at as u64);
+
+        enc.encode_u32(burst.data_packets.len() as u32);
         for packet in &burst.data_packets {
-            for &sample in &packet.data {
-                binary_data.extend(&(sample as f32).to_le_bytes());
+            Self::encode_packet_binary(&mut enc, packet);
+        }
+
+        Ok(enc.into_vec())
+    }
+
+    /// 将 [`Self::export_burst_as_binary`] 产出的字节流还原为 [`TriggerBurst`]。
+    /// 原始格式里没有落盘的逐包质量/来源信息会以合理的默认值重建，
+    /// 随后复用 [`Self::calculate_quality_summary`] 基于还原出的样本重新算出批次级质量摘要，
+    /// 因此除了逐通道统计细节外，质量信息不会因为一次导出/导入而丢失。
+    pub fn import_trigger_burst(&self, bytes: &[u8]) -> Result<TriggerBurst> {
+        let mut dec = Decoder::new(bytes);
+
+        let magic = dec.read_bytes(BINARY_FORMAT_MAGIC.len())?;
+        if magic != BINARY_FORMAT_MAGIC {
+            return Err(anyhow::anyhow!("Not a recognized trigger burst binary blob"));
+        }
+        let version = dec.decode_uint(1)? as u8;
+        if version != BINARY_FORMAT_VERSION {
+            return Err(anyhow::anyhow!("Unsupported binary burst format version: {}", version));
+        }
+
+        let burst_id = String::from_utf8(dec.decode_vvec()?.to_vec())
+            .map_err(|e| anyhow::anyhow!("Invalid UTF-8 in burst_id: {}", e))?;
+        let trigger_timestamp = dec.decode_u32()?;
+        let trigger_channel = dec.decode_uint(2)? as u16;
+        let pre_samples = dec.decode_u32()?;
+        let post_samples = dec.decode_u32()?;
+        let created_at = dec.decode_uint(8)? as i64;
+
+        let packet_count = dec.decode_u32()?;
+        let mut data_packets = Vec::with_capacity(packet_count as usize);
+        for index in 0..packet_count {
+            let timestamp = dec.decode_uint(8)?;
+            let sequence = dec.decode_uint(8)?;
+            let channel_count = dec.decode_u32()? as usize;
+            let sample_rate = f64::from_bits(dec.decode_uint(8)?);
+
+            let sample_bytes = dec.decode_vvec()?;
+            if sample_bytes.len() % 8 != 0 {
+                return Err(anyhow::anyhow!("Truncated sample vector in binary burst data"));
             }
+            let data: Vec<f64> = sample_bytes
+                .chunks_exact(8)
+                .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+
+            data_packets.push(ProcessedData {
+                timestamp,
+                sequence,
+                channel_count,
+                sample_rate,
+                data,
+                metadata: DataMetadata {
+                    packet_count: 1,
+                    processing_time_us: 0,
+                    data_quality: DataQuality::Good,
+                    channel_info: Vec::new(),
+                },
+                data_type: ProcessedDataType {
+                    source: DataSource::Trigger,
+                    trigger_info: Some(TriggerInfo {
+                        trigger_timestamp,
+                        is_complete: true,
+                        sequence_in_burst: Some(index),
+                    }),
+                },
+            });
         }
-        
-        Ok(binary_data)
+
+        let total_samples = data_packets.iter().map(|p| p.data.len()).sum();
+        let mut burst = TriggerBurst {
+            burst_id,
+            trigger_timestamp,
+            trigger_channel,
+            pre_samples,
+            post_samples,
+            data_packets,
+            is_complete: true,
+            total_samples,
+            created_at,
+            quality_summary: DataQualitySummary {
+                overall_quality: DataQuality::Good,
+                channel_stats: Vec::new(),
+                value_range: (f64::INFINITY, f64::NEG_INFINITY),
+                anomaly_count: 0,
+            },
+        };
+        self.calculate_quality_summary(&mut burst);
+
+        Ok(burst)
     }
 
     /// 重置触发状态（在模式切换时调用）
@@ -574,23 +1234,77 @@ impl DataProcessor {
         self.current_trigger_burst = None;
     }
 
-    /// 获取当前处理统计
+    /// 设备重连后调用：重连后 `DeviceManager` 会把 `DataPacket::sequence` 的扩展序号
+    /// 从头计起（见 `DeviceManager::reset_data_packet_sequence_tracking`），继续沿用
+    /// 断线前的跟踪状态会把这次重连误判成一次巨大的丢包。断线期间真实丢失的包无法
+    /// 再事后统计，这里只是避免产生一次错误的缺口告警，不编造具体缺口范围。
+    pub fn reset_sequence_tracking(&mut self) {
+        self.sequence_tracker = SequenceTracker::new();
+    }
+
+    /// 获取当前处理统计。`cached_bursts_count` 现在反映 sled 目录中持久化的批次总数
+    /// （跨重启累计），而非内存热缓存大小，更贴近"有多少历史批次可查"这个问题
     pub fn get_stats(&self) -> ProcessingStats {
         ProcessingStats {
             total_packets_processed: self.packet_sequence,
             current_trigger_burst_sequence: self.trigger_burst_sequence,
             current_trigger_timestamp: self.current_trigger_timestamp,
-            cached_bursts_count: self.completed_trigger_bursts.len(),
+            cached_bursts_count: self.by_time_tree.len(),
             current_burst_active: self.current_trigger_burst.is_some(),
+            cobs_frame_errors: self.cobs_frame_errors,
+            pre_trigger_ring_capacity: PRE_TRIGGER_RING_CAPACITY,
+            pre_trigger_ring_fill: self.pre_trigger_ring.fill_level(),
+            batch_size_histogram: self.batch_size_histogram(),
+            burst_cache_capacity: self.completed_trigger_bursts.cap().get(),
+            burst_cache_len: self.completed_trigger_bursts.len(),
+            burst_cache_hits: self.cache_hits,
+            burst_cache_misses: self.cache_misses,
+            last_evicted_burst_id: self.last_evicted_burst_id.clone(),
+            sequence_gap_stats: self.sequence_gap_stats(),
         }
     }
+
+    /// 当前正在累积的触发批次占用的内存（粗略估算：样本数 × 8 字节）
+    pub fn current_burst_buffer_bytes(&self) -> u64 {
+        self.current_trigger_burst.as_ref()
+            .map(|b| (b.total_samples * std::mem::size_of::<f64>()) as u64)
+            .unwrap_or(0)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ProcessingStats {
     pub total_packets_processed: u64,
     pub current_trigger_burst_sequence: u32,
     pub current_trigger_timestamp: Option<u32>,
     pub cached_bursts_count: usize,
     pub current_burst_active: bool,
+    /// [`DataProcessor::decode_frames`] 因 CRC-32 校验失败或 COBS 解码异常而丢弃的帧总数
+    pub cobs_frame_errors: u64,
+    /// 预触发环形缓冲区的固定容量（包数）
+    pub pre_trigger_ring_capacity: usize,
+    /// 预触发环形缓冲区当前已写入的包数（达到容量后不再增长，只会被覆盖）
+    pub pre_trigger_ring_fill: usize,
+    /// `process_packets` 批次大小分布，按批次大小固定分桶（上界见 `BATCH_SIZE_BUCKET_BOUNDS`）
+    pub batch_size_histogram: Vec<BatchSizeBucket>,
+    /// 内存热缓存（LRU）的容量，由 [`DataProcessor::new`] 固定为 10
+    pub burst_cache_capacity: usize,
+    /// 当前缓存里的批次数量
+    pub burst_cache_len: usize,
+    /// `get_trigger_burst` 命中内存热缓存的次数
+    pub burst_cache_hits: u64,
+    /// `get_trigger_burst` 未命中、需要从 sled 懒加载的次数
+    pub burst_cache_misses: u64,
+    /// 最近一次被 LRU 淘汰出缓存的批次 id（批次本身仍在 sled 目录中）
+    pub last_evicted_burst_id: Option<String>,
+    /// 设备数据包的丢包/重传/重排统计，见 [`DataProcessor::track_packet_sequence`]
+    pub sequence_gap_stats: SequenceGapStats,
+}
+
+/// `batch_size_histogram` 里的一个分桶：`upper_bound` 为 `None` 表示溢出桶
+/// （大于 [`BATCH_SIZE_BUCKET_BOUNDS`] 里列出的最大上界）
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BatchSizeBucket {
+    pub upper_bound: Option<usize>,
+    pub count: u64,
 }
\ No newline at end of file
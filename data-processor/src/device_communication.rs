@@ -1,10 +1,12 @@
 use anyhow::{anyhow, Result};
 use bytes::{Buf, BytesMut};
+use data_encoding::BASE64;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot, watch};
 use tokio_serial::{SerialPortBuilderExt, SerialStream};
 use tracing::{debug, error, info, warn};
 
@@ -12,12 +14,26 @@ use tracing::{debug, error, info, warn};
 const FRAME_HEAD: [u8; 2] = [0xAA, 0x55];
 const FRAME_TAIL: [u8; 2] = [0x55, 0xAA];
 
+/// 等待设备 ACK/NACK 的单次超时
+const ACK_TIMEOUT: Duration = Duration::from_millis(800);
+/// 超时未收到响应时的最大重传次数（不含首次发送）
+const MAX_COMMAND_RETRIES: u8 = 3;
+
+/// blob 下载单个分片的最大负载（固件/校准表等大块数据按此切片）
+const BLOB_CHUNK_SIZE: usize = 1024;
+/// blob 分片标志位：第一个分片
+const BLOB_FLAG_BEGIN: u8 = 0x01;
+/// blob 分片标志位：最后一个分片（单分片 blob 可同时具备 BEGIN|END）
+const BLOB_FLAG_END: u8 = 0x02;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceConfig {
     pub connection_type: ConnectionType,
     pub serial_port: Option<String>,
     pub socket_address: Option<String>,
     pub baud_rate: u32,
+    /// 线路协议："binary"（默认，AA55 二进制帧）或 "json_lines"（换行分隔 JSON，便于调试）
+    pub protocol: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +57,11 @@ pub struct DataPacket {
     pub sample_count: u16,      // 修正字段名
     pub sensor_data: Vec<u8>,
     pub data_type: DataType,    // 新增：区分数据类型
+    /// 由 `RawFrame::sequence`（8 bit 回绕）展开而来的扩展序号（见
+    /// [`DeviceManager::extend_data_packet_sequence`]），用于检测丢包/乱序；
+    /// 展开后可以直接复用 [`crate::ipc::SequenceTracker`] 的环上距离算法，而不会把
+    /// 正常的每 256 包一次 8 bit 回绕误判成丢包
+    pub sequence: u16,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,7 +73,7 @@ pub enum DataType {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TriggerEvent {
     pub timestamp: u32,
     pub channel: u16,
@@ -71,6 +92,8 @@ pub enum DeviceEvent {
     BufferTransferComplete,            // 新增：缓冲传输完成
     LogMessage { level: u8, message: String },
     Error(String),
+    /// blob 下载进度：每个分片被设备确认（ACK）后发出一次
+    DownloadProgress { blob_type: u8, sent: usize, total: usize },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,7 +112,7 @@ pub struct ChannelConfig {
     pub format: u8,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DeviceCommand {
     Ping,
     GetDeviceInfo,
@@ -99,6 +122,9 @@ pub enum DeviceCommand {
     StopStream,
     ConfigureStream { channels: Vec<ChannelConfig> },
     RequestBufferedData,               // 新增：请求缓冲数据
+    Reconfigure(DeviceConfig),         // 新增：运行时切换连接方式/地址/波特率
+    /// 向设备分片下发一块数据（固件镜像、校准表/LUT 等），每片经可靠投递路径确认后再发下一片
+    DownloadBlob { blob_type: u8, data: Vec<u8> },
 }
 
 /// 连接抽象：串口 / TCP（全异步）
@@ -145,21 +171,25 @@ impl Connection {
     }
 }
 
-/// 协议解析器
-pub struct ProtocolParser {
+/// 可插拔的帧编解码器：`DeviceManager` 只依赖这个 trait，不关心具体线路格式是二进制
+/// 还是文本协议，便于接入模拟器或其它固件而无需改动命令分发/ACK 逻辑。
+pub trait FrameCodec: Send {
+    /// 喂入新读到的字节，返回本次新解出的完整帧（内部维护一个累积缓冲区处理粘包/半包）
+    fn feed_data(&mut self, data: &[u8]) -> Result<Vec<RawFrame>>;
+    /// 将一条命令编码为可直接写入连接的字节序列
+    fn build_frame(&self, command: u8, seq: u8, payload: &[u8]) -> Vec<u8>;
+}
+
+/// 二进制帧协议解析器（AA55 帧头 / 55AA 帧尾 + CRC16），设备默认使用的协议
+pub struct BinaryCodec {
     buf: BytesMut,
 }
 
-impl ProtocolParser {
+impl BinaryCodec {
     pub fn new() -> Self {
         Self { buf: BytesMut::with_capacity(64 * 1024) }
     }
 
-    pub fn feed_data(&mut self, data: &[u8]) -> Result<Vec<RawFrame>> {
-        self.buf.extend_from_slice(data);
-        self.parse_frames()
-    }
-
     fn parse_frames(&mut self) -> Result<Vec<RawFrame>> {
         let mut frames = Vec::new();
         // 最小帧：2头 +2长 +1cmd +1seq +2crc +2尾 = 10
@@ -262,7 +292,7 @@ impl ProtocolParser {
         crc
     }
 
-    pub fn build_frame(command: u8, seq: u8, payload: &[u8]) -> Vec<u8> {
+    fn encode(command: u8, seq: u8, payload: &[u8]) -> Vec<u8> {
         // LEN = 1(cmd) + 1(seq) + payload + 2(crc)
         let len = 1 + 1 + payload.len() + 2;
         let mut out = Vec::with_capacity(4 + len + 2);
@@ -278,11 +308,82 @@ impl ProtocolParser {
     }
 }
 
+impl FrameCodec for BinaryCodec {
+    fn feed_data(&mut self, data: &[u8]) -> Result<Vec<RawFrame>> {
+        self.buf.extend_from_slice(data);
+        self.parse_frames()
+    }
+
+    fn build_frame(&self, command: u8, seq: u8, payload: &[u8]) -> Vec<u8> {
+        Self::encode(command, seq, payload)
+    }
+}
+
+/// 文本调试协议：换行分隔的 JSON 帧，payload 以 base64 编码。相比二进制 AA55 帧，
+/// 可以直接用 nc/脚本与设备或模拟器交互，便于调试，但吞吐和体积都不如二进制协议。
+pub struct JsonLinesCodec {
+    buf: Vec<u8>,
+}
+
+impl JsonLinesCodec {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonFrameWire {
+    command_id: u8,
+    sequence: u8,
+    /// base64 编码的负载
+    payload: String,
+}
+
+impl FrameCodec for JsonLinesCodec {
+    fn feed_data(&mut self, data: &[u8]) -> Result<Vec<RawFrame>> {
+        self.buf.extend_from_slice(data);
+        let mut frames = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = &line[..line.len() - 1]; // 去掉换行符
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_slice::<JsonFrameWire>(line) {
+                Ok(wire) => {
+                    let payload = BASE64.decode(wire.payload.as_bytes()).unwrap_or_default();
+                    frames.push(RawFrame {
+                        command_id: wire.command_id,
+                        sequence: wire.sequence,
+                        payload,
+                        _timestamp: std::time::Instant::now(),
+                    });
+                }
+                Err(e) => {
+                    warn!("JsonLinesCodec: failed to decode frame line: {}", e);
+                }
+            }
+        }
+        Ok(frames)
+    }
+
+    fn build_frame(&self, command: u8, seq: u8, payload: &[u8]) -> Vec<u8> {
+        let wire = JsonFrameWire {
+            command_id: command,
+            sequence: seq,
+            payload: BASE64.encode(payload),
+        };
+        let mut line = serde_json::to_vec(&wire).unwrap_or_default();
+        line.push(b'\n');
+        line
+    }
+}
+
 /// 设备管理器
 pub struct DeviceManager {
     pub config: DeviceConfig,
     connection: Option<Connection>,
-    parser: ProtocolParser,
+    codec: Box<dyn FrameCodec>,
     status: DeviceStatus,
 
     // 对外事件
@@ -291,10 +392,24 @@ pub struct DeviceManager {
     command_rx: mpsc::UnboundedReceiver<DeviceCommand>,
 
     seq: u8,
-    
+
+    // 等待 ACK/NACK 的在途命令：按 seq 索引。收到匹配的 0x90/0x91 时据此 resolve。
+    // 一次 send_command_awaited 调用的所有重传共用同一个 seq 和同一个 sender；
+    // 超时放弃或收到响应后会移除对应条目，recycle 到该 seq 的后续请求不会与已清理的旧条目冲突。
+    pending_acks: HashMap<u8, oneshot::Sender<Result<()>>>,
+
     // 触发模式状态跟踪
     trigger_active: bool,
     current_trigger: Option<TriggerEvent>,
+
+    // 最近一次下发的通道配置，重连后据此重放 ConfigureStream（mode 和 stream_active 已在
+    // status 中持久跟踪，这里只补上 status 没有覆盖的那部分状态）
+    last_channel_config: Option<Vec<ChannelConfig>>,
+
+    // DATA_PACKET 的 RawFrame::sequence 只有 8 bit，每 256 个包就会回绕一次；这两个字段
+    // 把它展开成一个不回绕（在 u16 范围内）的扩展序号，见 [`Self::extend_data_packet_sequence`]
+    last_data_packet_seq: Option<u8>,
+    data_packet_seq_epoch: u16,
 }
 
 impl DeviceManager {
@@ -304,10 +419,15 @@ impl DeviceManager {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         let (cmd_tx, command_rx) = mpsc::unbounded_channel();
 
+        let codec: Box<dyn FrameCodec> = match config.protocol.as_str() {
+            "json_lines" => Box::new(JsonLinesCodec::new()),
+            _ => Box::new(BinaryCodec::new()),
+        };
+
         let me = Self {
             config,
             connection: None,
-            parser: ProtocolParser::new(),
+            codec,
             status: DeviceStatus {
                 connected: false,
                 device_id: None,
@@ -318,27 +438,80 @@ impl DeviceManager {
             event_tx,
             command_rx,
             seq: 0,
+            pending_acks: HashMap::new(),
             trigger_active: false,
             current_trigger: None,
+            last_channel_config: None,
+            last_data_packet_seq: None,
+            data_packet_seq_epoch: 0,
         };
         (me, event_rx, cmd_tx)
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    /// 把 8 bit 回绕的 `RawFrame::sequence` 展开成一个单调递增（在 u16 范围内回绕）的
+    /// 扩展序号：每次只按两次调用之间的回绕感知前进量（`raw.wrapping_sub(last_raw)`）
+    /// 累加，而不是直接把原始字节当 u16 用。这样正常的每 256 包一次 8 bit 回绕不会被
+    /// [`crate::ipc::SequenceTracker`] 的 u16 回绕窗口误判成一次真实丢包。
+    fn extend_data_packet_sequence(&mut self, raw: u8) -> u16 {
+        let delta = match self.last_data_packet_seq {
+            Some(last_raw) => raw.wrapping_sub(last_raw),
+            None => 0,
+        };
+        self.data_packet_seq_epoch = self.data_packet_seq_epoch.wrapping_add(delta as u16);
+        self.last_data_packet_seq = Some(raw);
+        self.data_packet_seq_epoch
+    }
+
+    /// 重连后调用：设备重连时 `RawFrame::sequence` 会从某个未知起点重新计数，继续沿用
+    /// 断线前的 epoch 会把断线期间真实丢失的包折进一次很小的 delta 里，从而被忽略。
+    /// 这里直接重置扩展序号跟踪——断线期间的丢包没法再事后精确统计，索性不编造一个
+    /// 具体缺口范围，只记一条日志说明这段时间的 gap 统计不可信。
+    fn reset_data_packet_sequence_tracking(&mut self) {
+        if self.last_data_packet_seq.take().is_some() {
+            warn!("Device reconnected mid-stream: data packet sequence tracking reset; \
+                   packet loss during the disconnect window will not appear in sequence gap stats");
+        }
+        self.data_packet_seq_epoch = 0;
+    }
+
+    /// `shutdown` 收到 `true` 时尽快退出：不会中途砍断正在进行的读/命令处理，
+    /// 只是不再发起新的重连尝试，并在下一个安全点（select 的间隙）返回
+    pub async fn run(&mut self, mut shutdown: watch::Receiver<bool>) -> Result<()> {
         loop {
+            if *shutdown.borrow() {
+                info!("Device manager shutting down");
+                return Ok(());
+            }
+
             // 连接
             if self.connection.is_none() {
-                match self.try_connect().await {
-                    Ok(_) => {
-                        self.status.connected = true;
-                        let _ = self.event_tx.send(DeviceEvent::Connected(format!("{:?}", self.config.connection_type)));
-                        // 初始 PING
-                        self.send_command(0x01, &[]).await?;
+                tokio::select! {
+                    res = self.try_connect() => {
+                        match res {
+                            Ok(_) => {
+                                self.status.connected = true;
+                                self.reset_data_packet_sequence_tracking();
+                                let _ = self.event_tx.send(DeviceEvent::Connected(format!("{:?}", self.config.connection_type)));
+                                // 初始 PING：等待设备 ACK，确认链路真正可用而不只是底层连接建立成功
+                                self.send_command_awaited(0x01, &[]).await?;
+                                // 重连后恢复上一次的 mode/通道配置/流状态；任意一步被设备拒绝都干净地中止，
+                                // 不影响本次连接本身（设备已连上，只是配置可能需要用户手动重新下发）
+                                if let Err(e) = self.restore_session().await {
+                                    warn!("Session restoration after reconnect failed: {}", e);
+                                }
+                            }
+                            Err(e) => {
+                                error!("Connect failed: {}", e);
+                                tokio::time::sleep(Duration::from_secs(2)).await;
+                                continue;
+                            }
+                        }
                     }
-                    Err(e) => {
-                        error!("Connect failed: {}", e);
-                        tokio::time::sleep(Duration::from_secs(2)).await;
-                        continue;
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            info!("Device manager shutting down while disconnected");
+                            return Ok(());
+                        }
                     }
                 }
             }
@@ -371,9 +544,24 @@ impl DeviceManager {
                             }
                         }
                     }
+
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            info!("Device manager shutting down");
+                            return Ok(());
+                        }
+                    }
                 }
             } else {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            info!("Device manager shutting down while disconnected");
+                            return Ok(());
+                        }
+                    }
+                }
             }
         }
     }
@@ -399,57 +587,152 @@ impl DeviceManager {
         Ok(())
     }
 
+    /// 重连后重放上一次生效的会话状态：mode -> 通道配置 -> （如果之前在流式采集）StartStream。
+    /// 每一步都经可靠 ACK 路径确认，任意一步出错立即中止后续步骤。
+    async fn restore_session(&mut self) -> Result<()> {
+        match self.status.mode.clone().as_deref() {
+            Some("continuous") => {
+                info!("Restoring mode after reconnect: continuous");
+                self.send_command_awaited(0x10, &[]).await?;
+            }
+            Some("trigger") => {
+                info!("Restoring mode after reconnect: trigger");
+                self.send_command_awaited(0x11, &[]).await?;
+            }
+            _ => {}
+        }
+
+        if let Some(channels) = self.last_channel_config.clone() {
+            info!("Restoring channel configuration after reconnect: {} channel(s)", channels.len());
+            let payload = Self::build_configure_payload(&channels);
+            self.send_command_awaited(0x14, &payload).await?;
+        }
+
+        if self.status.stream_active {
+            info!("Restoring stream state after reconnect: active");
+            self.send_command_awaited(0x12, &[]).await?;
+        }
+
+        Ok(())
+    }
+
+    fn build_configure_payload(channels: &[ChannelConfig]) -> Vec<u8> {
+        // 简单示例：数量 + (id, rate, fmt)*
+        let mut payload = Vec::with_capacity(1 + channels.len() * 7);
+        payload.push(channels.len() as u8);
+        for ch in channels {
+            payload.push(ch.channel_id);
+            payload.extend_from_slice(&ch.sample_rate.to_le_bytes());
+            payload.push(ch.format);
+        }
+        payload
+    }
+
     async fn handle_command(&mut self, cmd: DeviceCommand) -> Result<()> {
         match cmd {
-            DeviceCommand::Ping => self.send_command(0x01, &[]).await,
-            DeviceCommand::GetDeviceInfo => self.send_command(0x03, &[]).await,
+            DeviceCommand::Ping => self.send_command_awaited(0x01, &[]).await,
+            DeviceCommand::GetDeviceInfo => self.send_command_awaited(0x03, &[]).await,
             DeviceCommand::SetModeContinuous => {
                 self.trigger_active = false;
                 self.current_trigger = None;
                 self.status.mode = Some("continuous".to_string());
-                self.send_command(0x10, &[]).await
+                self.send_command_awaited(0x10, &[]).await
             },
             DeviceCommand::SetModeTrigger => {
                 self.trigger_active = true;
                 self.current_trigger = None;
                 self.status.mode = Some("trigger".to_string());
-                self.send_command(0x11, &[]).await
+                self.send_command_awaited(0x11, &[]).await
             },
             DeviceCommand::StartStream => {
                 self.status.stream_active = true;
-                self.send_command(0x12, &[]).await
+                self.send_command_awaited(0x12, &[]).await
             },
             DeviceCommand::StopStream => {
                 self.status.stream_active = false;
-                self.send_command(0x13, &[]).await
+                self.send_command_awaited(0x13, &[]).await
             },
             DeviceCommand::ConfigureStream { channels } => {
-                // 简单示例：数量 + (id, rate, fmt)*
-                let mut payload = Vec::with_capacity(1 + channels.len()*7);
-                payload.push(channels.len() as u8);
-                for ch in channels {
-                    payload.push(ch.channel_id);
-                    payload.extend_from_slice(&ch.sample_rate.to_le_bytes());
-                    payload.push(ch.format);
-                }
-                self.send_command(0x14, &payload).await
+                let payload = Self::build_configure_payload(&channels);
+                self.last_channel_config = Some(channels);
+                self.send_command_awaited(0x14, &payload).await
             },
             DeviceCommand::RequestBufferedData => {
                 if self.trigger_active {
                     info!("Requesting buffered trigger data");
-                    self.send_command(0x42, &[]).await
+                    self.send_command_awaited(0x42, &[]).await
                 } else {
                     warn!("RequestBufferedData called but not in trigger mode");
                     Ok(())
                 }
             }
+            DeviceCommand::Reconfigure(new_config) => {
+                info!("Reconfiguring device connection: {:?}", new_config);
+                self.config = new_config;
+                // 断开当前连接，下一轮 run() 循环会用新配置重新连接
+                if self.connection.take().is_some() {
+                    self.status.connected = false;
+                    let _ = self.event_tx.send(DeviceEvent::Disconnected);
+                }
+                Ok(())
+            }
+            DeviceCommand::DownloadBlob { blob_type, data } => {
+                self.download_blob(blob_type, &data).await
+            }
         }
     }
 
-    async fn send_command(&mut self, command: u8, payload: &[u8]) -> Result<()> {
+    /// 将 `data` 按 `BLOB_CHUNK_SIZE` 切片，逐片经可靠投递路径（0x20）下发给设备；
+    /// 每片负载为 flag(1) + blob_type(1) + chunk_len(u16 LE) + crc16(u16 LE) + chunk，
+    /// 每片都要等到设备 ACK 后才发下一片。空 `data` 会作为一个同时带 BEGIN|END 的空分片发送。
+    async fn download_blob(&mut self, blob_type: u8, data: &[u8]) -> Result<()> {
+        let total = data.len();
+        if total == 0 {
+            let payload = Self::build_blob_chunk_payload(BLOB_FLAG_BEGIN | BLOB_FLAG_END, blob_type, &[]);
+            self.send_command_awaited(0x20, &payload).await?;
+            let _ = self.event_tx.send(DeviceEvent::DownloadProgress { blob_type, sent: 0, total: 0 });
+            return Ok(());
+        }
+
+        let chunks: Vec<&[u8]> = data.chunks(BLOB_CHUNK_SIZE).collect();
+        let last = chunks.len() - 1;
+        let mut sent = 0usize;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let mut flag = 0u8;
+            if i == 0 {
+                flag |= BLOB_FLAG_BEGIN;
+            }
+            if i == last {
+                flag |= BLOB_FLAG_END;
+            }
+            let payload = Self::build_blob_chunk_payload(flag, blob_type, chunk);
+            self.send_command_awaited(0x20, &payload).await?;
+            sent += chunk.len();
+            debug!("Blob chunk acked: type={} flag=0x{:02X} sent={}/{}", blob_type, flag, sent, total);
+            let _ = self.event_tx.send(DeviceEvent::DownloadProgress { blob_type, sent, total });
+        }
+        Ok(())
+    }
+
+    fn build_blob_chunk_payload(flag: u8, blob_type: u8, chunk: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(6 + chunk.len());
+        payload.push(flag);
+        payload.push(blob_type);
+        payload.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+        payload.extend_from_slice(&BinaryCodec::crc16(chunk).to_le_bytes());
+        payload.extend_from_slice(chunk);
+        payload
+    }
+
+    /// 分配下一个帧序号（u8 回绕）
+    fn next_seq(&mut self) -> u8 {
         let seq = self.seq;
         self.seq = self.seq.wrapping_add(1);
-        let frame = ProtocolParser::build_frame(command, seq, payload);
+        seq
+    }
+
+    async fn write_frame(&mut self, command: u8, seq: u8, payload: &[u8]) -> Result<()> {
+        let frame = self.codec.build_frame(command, seq, payload);
         if let Some(conn) = self.connection.as_mut() {
             conn.write(&frame).await?;
             debug!("Sent cmd=0x{:02X} seq={}", command, seq);
@@ -459,8 +742,72 @@ impl DeviceManager {
         }
     }
 
+    /// 可靠命令投递：注册一个按 seq 索引的 ACK/NACK waiter，写帧后在 `ACK_TIMEOUT` 内等待响应；
+    /// 超时则重传同一个 seq+payload，最多 `MAX_COMMAND_RETRIES` 次后放弃并返回错误。
+    /// 收到 0x90（ACK）resolve 为 `Ok(())`；收到 0x91（NACK）resolve 为解码后的错误。
+    async fn send_command_awaited(&mut self, command: u8, payload: &[u8]) -> Result<()> {
+        let seq = self.next_seq();
+        let (tx, mut rx) = oneshot::channel();
+        self.pending_acks.insert(seq, tx);
+
+        let mut last_err = anyhow!("no connection");
+        for attempt in 1..=1 + MAX_COMMAND_RETRIES {
+            if let Err(e) = self.write_frame(command, seq, payload).await {
+                self.pending_acks.remove(&seq);
+                return Err(e);
+            }
+            debug!(
+                "Awaiting ACK for cmd=0x{:02X} seq={} (attempt {}/{})",
+                command, seq, attempt, 1 + MAX_COMMAND_RETRIES
+            );
+
+            match tokio::time::timeout(ACK_TIMEOUT, self.wait_for_ack(&mut rx)).await {
+                Ok(result) => {
+                    self.pending_acks.remove(&seq);
+                    return result;
+                }
+                Err(_) => {
+                    warn!(
+                        "cmd=0x{:02X} seq={} timed out waiting for ACK (attempt {}/{})",
+                        command, seq, attempt, 1 + MAX_COMMAND_RETRIES
+                    );
+                    last_err = anyhow!(
+                        "cmd=0x{:02X} seq={} timed out after {} attempt(s)",
+                        command, seq, attempt
+                    );
+                }
+            }
+        }
+
+        // 放弃等待：清理 waiter，之后这个 seq 的迟到响应找不到 waiter，只记一条 debug 日志。
+        // 响应只按 u8 seq 匹配，不区分请求的先后：迟到响应若在 seq 回绕、被新请求复用之后
+        // 才到达，会被当作那个新请求的响应。
+        self.pending_acks.remove(&seq);
+        Err(last_err)
+    }
+
+    /// 在等待某个 seq 的 ACK/NACK 期间持续从连接读取字节并照常派发帧（数据包、触发事件等
+    /// 非响应帧不受影响），直到 `handle_frame` resolve 了对应的 waiter 或连接出错。
+    async fn wait_for_ack(&mut self, rx: &mut oneshot::Receiver<Result<()>>) -> Result<()> {
+        loop {
+            let conn = self.connection.as_mut().ok_or_else(|| anyhow!("no connection"))?;
+            let mut buf = [0u8; 4096];
+            tokio::select! {
+                biased;
+                resolved = rx => {
+                    return resolved.unwrap_or_else(|_| Err(anyhow!("ack waiter dropped")));
+                }
+                res = conn.read(&mut buf) => {
+                    let n = res?;
+                    let bytes = buf[..n].to_vec();
+                    self.process_bytes(&bytes).await?;
+                }
+            }
+        }
+    }
+
     async fn process_bytes(&mut self, data: &[u8]) -> Result<()> {
-        let frames = self.parser.feed_data(data)?;
+        let frames = self.codec.feed_data(data)?;
         for f in frames {
             self.handle_frame(f).await?;
         }
@@ -509,12 +856,14 @@ impl DeviceManager {
                     debug!("DATA packet: ts={}, channels=0x{:04X}, samples={}, type={:?}", 
                            ts, enabled_channels, sample_count, data_type);
                     
+                    let sequence = self.extend_data_packet_sequence(f.sequence);
                     let pkt = DataPacket {
                         timestamp_ms: ts,
                         enabled_channels,
                         sample_count,
                         sensor_data: data,
                         data_type,
+                        sequence,
                     };
                     let _ = self.event_tx.send(DeviceEvent::DataPacket(pkt));
                 }
@@ -558,22 +907,34 @@ impl DeviceManager {
             }
             0x90 => { // ACK
                 debug!("ACK seq={}", f.sequence);
+                if let Some(tx) = self.pending_acks.remove(&f.sequence) {
+                    let _ = tx.send(Ok(()));
+                } else {
+                    debug!("ACK seq={} has no matching waiter (already resolved/timed out, or stale)", f.sequence);
+                }
             }
             0x91 => { // NACK
                 warn!("NACK seq={} payload={:X?}", f.sequence, f.payload);
-                if f.payload.len() >= 2 {
+                let error_msg = if f.payload.len() >= 2 {
                     let error_type = f.payload[0];
                     let error_code = f.payload[1];
-                    let error_msg = match (error_type, error_code) {
+                    match (error_type, error_code) {
                         (0x01, 0x01) => "Parameter error: invalid parameter".to_string(),
                         (0x01, 0x02) => "Parameter error: invalid channel configuration".to_string(),
                         (0x02, 0x01) => "Status error: invalid mode for operation".to_string(),
                         (0x02, 0x02) => "Status error: trigger not occurred".to_string(),
                         (0x05, 0x00) => "Command not supported".to_string(),
                         _ => format!("Unknown error: type={}, code={}", error_type, error_code),
-                    };
-                    let _ = self.event_tx.send(DeviceEvent::Error(error_msg));
+                    }
+                } else {
+                    "NACK with no error payload".to_string()
+                };
+                if let Some(tx) = self.pending_acks.remove(&f.sequence) {
+                    let _ = tx.send(Err(anyhow!(error_msg.clone())));
+                } else {
+                    debug!("NACK seq={} has no matching waiter (already resolved/timed out, or stale)", f.sequence);
                 }
+                let _ = self.event_tx.send(DeviceEvent::Error(error_msg));
             }
             0xE0 => { // LOG_MESSAGE
                 if f.payload.len() >= 2 {
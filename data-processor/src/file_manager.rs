@@ -1,11 +1,41 @@
 use anyhow::{anyhow, Result};
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client as S3Client;
+use bytes::Bytes;
 use chrono::Utc;
+use crate::config::{S3Config, StorageConfig};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::{stream, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Read;
+use std::io::{self, Read, Write};
 use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use tar::{Builder as TarBuilder, Header as TarHeader};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 流式下载时每次读取/产出的固定缓冲大小，内存占用与文件大小无关
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 一个字节流，`Stream` 产出固定大小的 `Bytes` 块，供 axum 响应体直接消费
+type ByteStreamBody = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// 滚动哈希窗口大小（buzhash）
+const ROLLING_WINDOW: usize = 64;
+/// 期望平均块大小 64 KiB：hash & MASK == 0 时认为是一个块边界
+const CHUNK_MASK: u64 = 64 * 1024 - 1;
+const CHUNK_MIN: usize = 16 * 1024;
+const CHUNK_MAX: usize = 256 * 1024;
+/// 块内容存放目录（相对 base），文件名为其 BLAKE3 十六进制哈希
+const CHUNKS_DIR: &str = "chunks";
+/// 每个逻辑文件的分块清单，存放在 "<原相对路径><后缀>"
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct FileInfo {
     pub filename: String,   // 相对 base 的路径（包含子目录时形如 "dir/name.ext"）
     pub size_bytes: u64,
@@ -13,6 +43,290 @@ pub struct FileInfo {
     pub file_type: String,
 }
 
+/// 记录一个逻辑文件由哪些块按顺序拼接而成
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChunkManifest {
+    chunk_hashes: Vec<String>,
+    total_len: u64,
+}
+
+/// 保留策略：三项约束各自可选，都为 `None` 等于不清理；`cleanup` 会递归扫描整棵目录树
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RetentionPolicy {
+    /// 最多保留的文件数，超出的部分从最旧开始删
+    pub max_files: Option<usize>,
+    /// 累计逻辑字节数上限，超出的部分从最旧开始删
+    pub max_total_bytes: Option<u64>,
+    /// 最大保留时长（毫秒），超过此年龄的文件直接删除（不受 `max_files`/`max_total_bytes` 影响）
+    pub max_age_ms: Option<i64>,
+}
+
+/// `cleanup` 的执行结果，供调用方记录日志
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CleanupSummary {
+    pub removed_count: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// `GET /api/files/stats` 的统计结果：逻辑字节数 vs. 物理（去重后）字节数
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct StorageStats {
+    /// 所有文件未去重时的总大小
+    pub logical_bytes: u64,
+    /// 实际占用的磁盘空间（块去重后 + 未分块文件）
+    pub physical_bytes: u64,
+    pub chunk_count: usize,
+    pub manifest_count: usize,
+    /// logical_bytes / physical_bytes；无数据时为 1.0
+    pub dedup_ratio: f64,
+}
+
+/// 基于 64 字节滑动窗口的 buzhash 系数表（splitmix64 派生，固定种子，确定性）
+fn buzhash_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    for (i, slot) in table.iter_mut().enumerate() {
+        let mut z = (i as u64).wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        *slot = z ^ (z >> 31);
+    }
+    table
+}
+
+/// 内容定义分块（CDC）：用 buzhash 滚动哈希寻找边界，限制块大小在 [CHUNK_MIN, CHUNK_MAX] 之间
+fn split_chunks(data: &[u8]) -> Vec<&[u8]> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let table = buzhash_table();
+    let mut out = Vec::new();
+    let mut start = 0usize;
+    let mut hash: u64 = 0;
+
+    for (i, &byte) in data.iter().enumerate() {
+        hash = hash.rotate_left(1) ^ table[byte as usize];
+        let len = i + 1 - start;
+        if i >= ROLLING_WINDOW {
+            let out_byte = data[i - ROLLING_WINDOW];
+            hash ^= table[out_byte as usize].rotate_left((ROLLING_WINDOW % 64) as u32);
+        }
+        if (len >= CHUNK_MIN && hash & CHUNK_MASK == 0) || len >= CHUNK_MAX {
+            out.push(&data[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < data.len() {
+        out.push(&data[start..]);
+    }
+    out
+}
+
+fn determine_file_type(filename: &str) -> String {
+    let lower = filename.to_ascii_lowercase();
+    if lower.ends_with(".txt") {
+        "raw_frames".to_string()
+    } else if lower.ends_with(".bin") || lower.ends_with(".dat") {
+        "binary".to_string()
+    } else if lower.ends_with(".json") {
+        "json".to_string()
+    } else {
+        "unknown".to_string()
+    }
+}
+
+/// 组件级路径审计器，用于在 `safe_join` 中关闭符号链接造成的沙箱逃逸/TOCTOU 缺口
+///
+/// 仅 canonicalize 最终路径不够：若 base 内部某个已存在的中间目录本身是指向
+/// base 之外的符号链接，最终路径 canonicalize 后仍可能落在 base 之内（例如链接
+/// 指回 base 下的另一个位置），但实际读写会经过链接逃逸出去；且两次 canonicalize
+/// 之间文件系统可能发生变化。本审计器从 base 出发逐级展开 `rel` 的每个组件，对
+/// 每个已存在的中间项做符号链接检查，并在单次调用内缓存已审计过的前缀，避免
+/// 对同一祖先目录重复 `readlink`/`canonicalize`。
+struct PathAuditor<'a> {
+    canon_base: &'a Path,
+    audited: HashMap<PathBuf, bool>,
+}
+
+impl<'a> PathAuditor<'a> {
+    fn new(canon_base: &'a Path) -> Self {
+        Self { canon_base, audited: HashMap::new() }
+    }
+
+    /// 逐级校验 `rel` 在 base 下展开后的每个已存在组件；不存在的组件（例如尚未
+    /// 创建的目录/文件）直接跳过，交由调用方决定是否允许创建
+    fn audit(&mut self, rel: &Path) -> Result<()> {
+        let mut current = self.canon_base.to_path_buf();
+        for comp in rel.components() {
+            current.push(comp.as_os_str());
+
+            if let Some(&ok) = self.audited.get(&current) {
+                if !ok {
+                    return Err(anyhow!("path escapes base directory via symlink: {}", current.display()));
+                }
+                continue;
+            }
+
+            let ok = match fs::symlink_metadata(&current) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    let target = fs::canonicalize(&current)?;
+                    target.starts_with(self.canon_base)
+                }
+                Ok(_) | Err(_) => true,
+            };
+            self.audited.insert(current.clone(), ok);
+            if !ok {
+                return Err(anyhow!("path escapes base directory via symlink: {}", current.display()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// S3 兼容对象存储目标：`dir`/`filename` 映射为 `<prefix>/<dir>/<filename>` 对象 key
+struct S3Target {
+    client: S3Client,
+    bucket: String,
+    prefix: String,
+    endpoint: String,
+}
+
+impl S3Target {
+    fn new(cfg: &S3Config) -> Self {
+        let creds = Credentials::new(&cfg.access_key, &cfg.secret_key, None, None, "data-processor");
+        let s3_conf = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(&cfg.endpoint)
+            .credentials_provider(creds)
+            .force_path_style(true)
+            .build();
+        Self {
+            client: S3Client::from_conf(s3_conf),
+            bucket: cfg.bucket.clone(),
+            prefix: cfg.prefix.trim_matches('/').to_string(),
+            endpoint: cfg.endpoint.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn object_key(&self, rel: &str) -> String {
+        let rel = rel.trim_start_matches('/');
+        if self.prefix.is_empty() {
+            rel.to_string()
+        } else {
+            format!("{}/{}", self.prefix, rel)
+        }
+    }
+
+    fn object_url(&self, rel: &str) -> String {
+        format!("{}/{}/{}", self.endpoint, self.bucket, self.object_key(rel))
+    }
+
+    async fn put(&self, rel: &str, bytes: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.object_key(rel))
+            .body(ByteStream::from(bytes))
+            .send()
+            .await
+            .map_err(|e| anyhow!("s3 put_object failed: {}", e))?;
+        Ok(())
+    }
+
+    async fn get(&self, rel: &str) -> Result<Vec<u8>> {
+        let out = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.object_key(rel))
+            .send()
+            .await
+            .map_err(|e| anyhow!("s3 get_object failed: {}", e))?;
+        let data = out
+            .body
+            .collect()
+            .await
+            .map_err(|e| anyhow!("s3 read object body failed: {}", e))?;
+        Ok(data.into_bytes().to_vec())
+    }
+
+    async fn list(&self, rel_dir: Option<&str>) -> Result<Vec<FileInfo>> {
+        let list_prefix = match rel_dir {
+            Some(d) if !d.trim_matches('/').is_empty() => self.object_key(d.trim_matches('/')),
+            _ => self.prefix.clone(),
+        };
+
+        let resp = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(&list_prefix)
+            .send()
+            .await
+            .map_err(|e| anyhow!("s3 list_objects_v2 failed: {}", e))?;
+
+        let mut out = Vec::new();
+        for obj in resp.contents() {
+            let key = obj.key().unwrap_or_default();
+            let rel = if self.prefix.is_empty() {
+                key.to_string()
+            } else {
+                key.strip_prefix(&format!("{}/", self.prefix)).unwrap_or(key).to_string()
+            };
+            let created_at = obj
+                .last_modified()
+                .and_then(|dt| dt.to_millis().ok())
+                .unwrap_or_else(|| Utc::now().timestamp_millis());
+
+            out.push(FileInfo {
+                file_type: determine_file_type(&rel),
+                filename: rel,
+                size_bytes: obj.size().unwrap_or(0).max(0) as u64,
+                created_at,
+            });
+        }
+        out.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(out)
+    }
+
+    /// 仅取对象长度（HEAD），不下载内容，用于 Range 请求前计算 Content-Length
+    async fn head_len(&self, rel: &str) -> Result<u64> {
+        let out = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(self.object_key(rel))
+            .send()
+            .await
+            .map_err(|e| anyhow!("s3 head_object failed: {}", e))?;
+        Ok(out.content_length().unwrap_or(0).max(0) as u64)
+    }
+
+    /// 以流的形式获取对象内容；传入 `range` 时透传为 S3 的 `Range` 头，由对象存储自身完成区间裁剪
+    async fn get_stream(&self, rel: &str, range: Option<(u64, u64)>) -> Result<ByteStreamBody> {
+        let mut req = self.client.get_object().bucket(&self.bucket).key(self.object_key(rel));
+        if let Some((start, end)) = range {
+            req = req.range(format!("bytes={}-{}", start, end));
+        }
+        let out = req.send().await.map_err(|e| anyhow!("s3 get_object failed: {}", e))?;
+        let stream = out
+            .body
+            .map(|chunk| chunk.map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+        Ok(Box::pin(stream))
+    }
+
+    async fn delete(&self, rel: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.object_key(rel))
+            .send()
+            .await
+            .map_err(|e| anyhow!("s3 delete_object failed: {}", e))?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessedDataFile {
     pub filename: String,   // 仅文件名（不含目录）
@@ -21,20 +335,37 @@ pub struct ProcessedDataFile {
 
 pub struct FileManager {
     base: PathBuf,
+    /// 配置了 `storage.backend = "s3"` 时使用对象存储代替本地磁盘
+    s3: Option<S3Target>,
 }
 
 impl FileManager {
-    pub fn new<P: AsRef<Path>>(data_directory: P) -> Result<Self> {
-        let p = data_directory.as_ref().to_path_buf();
+    /// 根据 `StorageConfig` 初始化：始终确保本地 `data_dir` 存在（用于 chunk 去重存储），
+    /// backend = "s3" 时额外初始化对象存储客户端，save/read/list/cleanup 将转发给它。
+    pub fn new(storage: &StorageConfig) -> Result<Self> {
+        let p = PathBuf::from(&storage.data_dir);
         if !p.exists() {
             fs::create_dir_all(&p)
                 .map_err(|e| anyhow!("create data dir {:?} failed: {}", &p, e))?;
         }
-        Ok(Self { base: p })
+        let s3 = match storage.backend.as_str() {
+            "s3" => {
+                let s3_cfg = storage.s3.as_ref().ok_or_else(|| {
+                    anyhow!("storage.backend is 's3' but storage.s3 is not configured")
+                })?;
+                Some(S3Target::new(s3_cfg))
+            }
+            _ => None,
+        };
+        Ok(Self { base: p, s3 })
     }
 
     /// 列出 base 或指定子目录下的文件（不递归）
-    pub fn list_files_in(&self, rel_dir: Option<&str>) -> Result<Vec<FileInfo>> {
+    pub async fn list_files_in(&self, rel_dir: Option<&str>) -> Result<Vec<FileInfo>> {
+        if let Some(s3) = &self.s3 {
+            return s3.list(rel_dir).await;
+        }
+
         let dir_path = if let Some(rd) = rel_dir {
             let safe = Self::sanitize_rel_path(rd)?;
             self.safe_join(&safe, true)?
@@ -46,6 +377,17 @@ impl FileManager {
         for entry in fs::read_dir(&dir_path)? {
             let entry = entry?;
             let path = entry.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+            if path.is_dir() && dir_path == self.base && name == CHUNKS_DIR {
+                continue; // 内部分块存储目录，不作为用户文件展示
+            }
+            if name.ends_with(MANIFEST_SUFFIX) {
+                if let Some(info) = self.get_manifest_file_info(&path)? {
+                    out.push(info);
+                }
+                continue;
+            }
             if let Some(info) = self.get_file_info(&path)? {
                 out.push(info);
             }
@@ -55,27 +397,297 @@ impl FileManager {
     }
 
     /// 兼容旧接口：列出 base 目录下文件（不递归）
-    pub fn list_files(&self) -> Result<Vec<FileInfo>> {
-        self.list_files_in(None)
+    pub async fn list_files(&self) -> Result<Vec<FileInfo>> {
+        self.list_files_in(None).await
+    }
+
+    /// 递归列出 base 或指定子目录下的文件，适合按日期/会话分了子目录存放的录制文件。
+    /// `max_depth` 限制往下递归的子目录层数（0 = 只看当前目录，不进入任何子目录），
+    /// 避免目录结构异常时无限遍历；`pattern` 非空时按 glob（`*`/`?`）匹配文件名（不含目录部分）。
+    /// 符号链接一律跳过，防止目录环路。排序规则与 `list_files_in` 一致：整棵树按创建时间倒序。
+    pub async fn list_files_recursive(
+        &self,
+        rel_dir: Option<&str>,
+        max_depth: usize,
+        pattern: Option<&str>,
+    ) -> Result<Vec<FileInfo>> {
+        if let Some(s3) = &self.s3 {
+            // 对象存储没有真正的目录层级，退化成一次平铺列举 + 按 pattern 过滤
+            let mut items = s3.list(rel_dir).await?;
+            if let Some(p) = pattern {
+                items.retain(|fi| Self::glob_match_filename(p, &fi.filename));
+            }
+            return Ok(items);
+        }
+
+        let dir_path = if let Some(rd) = rel_dir {
+            let safe = Self::sanitize_rel_path(rd)?;
+            self.safe_join(&safe, true)?
+        } else {
+            self.base.clone()
+        };
+
+        let mut out = Vec::new();
+        self.walk_dir_recursive(&dir_path, max_depth, pattern, &mut out)?;
+        out.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(out)
     }
 
-    /// 读取相对 base 的文件（支持子目录）
-    pub fn read_file(&self, rel_path: &str) -> Result<Vec<u8>> {
+    fn walk_dir_recursive(
+        &self,
+        dir_path: &Path,
+        remaining_depth: usize,
+        pattern: Option<&str>,
+        out: &mut Vec<FileInfo>,
+    ) -> Result<()> {
+        let chunks_dir = self.chunks_dir();
+
+        for entry in fs::read_dir(dir_path)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+            // 符号链接一律跳过：目录形式的符号链接可能造成环路，文件形式的也一并跳过以保持行为简单一致
+            if entry.file_type()?.is_symlink() {
+                continue;
+            }
+
+            if path.is_dir() {
+                if path == chunks_dir {
+                    continue; // 内部分块存储目录，不作为用户文件展示
+                }
+                if remaining_depth == 0 {
+                    continue;
+                }
+                self.walk_dir_recursive(&path, remaining_depth - 1, pattern, out)?;
+                continue;
+            }
+
+            if name.ends_with(MANIFEST_SUFFIX) {
+                if let Some(info) = self.get_manifest_file_info(&path)? {
+                    if pattern.map_or(true, |p| Self::glob_match_filename(p, &info.filename)) {
+                        out.push(info);
+                    }
+                }
+                continue;
+            }
+
+            if let Some(info) = self.get_file_info(&path)? {
+                if pattern.map_or(true, |p| Self::glob_match_filename(p, &info.filename)) {
+                    out.push(info);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 按 glob 通配符匹配 `filename` 的文件名部分（忽略目录前缀），支持 `*`（任意序列）和 `?`（单字符）
+    fn glob_match_filename(pattern: &str, filename: &str) -> bool {
+        let base = filename.rsplit('/').next().unwrap_or(filename);
+        Self::glob_match(pattern, base)
+    }
+
+    /// 标准的双指针通配符匹配算法，支持 `*` 和 `?`
+    fn glob_match(pattern: &str, text: &str) -> bool {
+        let p = pattern.as_bytes();
+        let t = text.as_bytes();
+        let (mut pi, mut ti) = (0usize, 0usize);
+        let mut star_idx: Option<usize> = None;
+        let mut match_idx = 0usize;
+
+        while ti < t.len() {
+            if pi < p.len() && (p[pi] == b'?' || p[pi] == t[ti]) {
+                pi += 1;
+                ti += 1;
+            } else if pi < p.len() && p[pi] == b'*' {
+                star_idx = Some(pi);
+                match_idx = ti;
+                pi += 1;
+            } else if let Some(si) = star_idx {
+                pi = si + 1;
+                match_idx += 1;
+                ti = match_idx;
+            } else {
+                return false;
+            }
+        }
+        while pi < p.len() && p[pi] == b'*' {
+            pi += 1;
+        }
+        pi == p.len()
+    }
+
+    /// 读取相对 base 的文件（支持子目录）。若该文件是分块存储的，按清单顺序拼接还原。
+    #[allow(dead_code)]
+    pub async fn read_file(&self, rel_path: &str) -> Result<Vec<u8>> {
+        if let Some(s3) = &self.s3 {
+            return s3.get(rel_path).await;
+        }
+
         let full = self.safe_join(&Self::sanitize_rel_path(rel_path)?, false)?;
+        let manifest_path = self.manifest_path(&full);
+        if manifest_path.is_file() {
+            return self.read_chunked(&manifest_path);
+        }
         let mut f = fs::File::open(&full)?;
         let mut buf = Vec::new();
         f.read_to_end(&mut buf)?;
         Ok(buf)
     }
 
+    /// 逻辑文件总字节数，不读取内容；用于 Range 请求前计算 `Content-Length`/校验区间
+    pub async fn file_len(&self, rel_path: &str) -> Result<u64> {
+        if let Some(s3) = &self.s3 {
+            return s3.head_len(rel_path).await;
+        }
+
+        let full = self.safe_join(&Self::sanitize_rel_path(rel_path)?, false)?;
+        let manifest_path = self.manifest_path(&full);
+        if manifest_path.is_file() {
+            let manifest: ChunkManifest = serde_json::from_slice(&fs::read(&manifest_path)?)?;
+            return Ok(manifest.total_len);
+        }
+        Ok(fs::metadata(&full)?.len())
+    }
+
+    /// 以固定大小缓冲流式读取文件（可选 `range` 闭区间字节偏移），内存占用恒定、与文件/区间大小无关。
+    /// 调用方需先用 [`FileManager::file_len`] 校验并裁剪好 `range`。
+    pub async fn read_file_stream(
+        &self,
+        rel_path: &str,
+        range: Option<(u64, u64)>,
+    ) -> Result<ByteStreamBody> {
+        if let Some(s3) = &self.s3 {
+            return s3.get_stream(rel_path, range).await;
+        }
+
+        let full = self.safe_join(&Self::sanitize_rel_path(rel_path)?, false)?;
+        let manifest_path = self.manifest_path(&full);
+        if manifest_path.is_file() {
+            return self.read_chunked_stream(&manifest_path, range).await;
+        }
+
+        let mut file = tokio::fs::File::open(&full).await?;
+        let (start, take_len) = match range {
+            Some((start, end)) => (start, end - start + 1),
+            None => (0, fs::metadata(&full)?.len()),
+        };
+        if start > 0 {
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+        }
+        let stream = ReaderStream::with_capacity(file.take(take_len), STREAM_CHUNK_SIZE);
+        Ok(Box::pin(stream))
+    }
+
+    /// 分块存储文件的流式读取：按清单顺序定位覆盖所请求区间的块，逐块异步读取后产出
+    async fn read_chunked_stream(
+        &self,
+        manifest_path: &Path,
+        range: Option<(u64, u64)>,
+    ) -> Result<ByteStreamBody> {
+        let manifest: ChunkManifest = serde_json::from_slice(&fs::read(manifest_path)?)?;
+        let chunks_dir = self.chunks_dir();
+        let (want_start, want_end) = range.unwrap_or((0, manifest.total_len.saturating_sub(1)));
+
+        let mut offset = 0u64;
+        let mut plan: Vec<(PathBuf, u64, u64)> = Vec::new(); // (chunk_path, skip_in_chunk, take)
+        for hash in &manifest.chunk_hashes {
+            let chunk_path = chunks_dir.join(hash);
+            let chunk_len = fs::metadata(&chunk_path)?.len();
+            let chunk_start = offset;
+            let chunk_end = offset + chunk_len - 1;
+            offset += chunk_len;
+            if chunk_len == 0 || chunk_end < want_start || chunk_start > want_end {
+                continue;
+            }
+            let skip = want_start.saturating_sub(chunk_start);
+            let take = chunk_end.min(want_end) - (chunk_start + skip) + 1;
+            plan.push((chunk_path, skip, take));
+        }
+
+        let out = stream::iter(plan).then(|(path, skip, take)| async move {
+            let mut f = tokio::fs::File::open(&path).await?;
+            if skip > 0 {
+                f.seek(std::io::SeekFrom::Start(skip)).await?;
+            }
+            let mut buf = vec![0u8; take as usize];
+            f.read_exact(&mut buf).await?;
+            Ok::<Bytes, io::Error>(Bytes::from(buf))
+        });
+        Ok(Box::pin(out))
+    }
+
+    /// 将多个文件打包成一个 tar（`gzip=true` 时外层再套一层 gzip）流写出。每个条目单独读取、
+    /// 单独写入 `writer`，不会把整份归档缓冲在内存里；每个路径都先经 `sanitize_rel_path`/
+    /// `safe_join` 校验过才会被读取，tar 条目名就是校验通过后的 base 相对路径。
+    pub async fn export_archive(&self, rel_paths: &[&str], writer: impl Write, gzip: bool) -> Result<()> {
+        if gzip {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            self.write_archive_entries(rel_paths, &mut encoder).await?;
+            encoder.finish()?;
+        } else {
+            let mut writer = writer;
+            self.write_archive_entries(rel_paths, &mut writer).await?;
+        }
+        Ok(())
+    }
+
+    async fn write_archive_entries(&self, rel_paths: &[&str], writer: &mut impl Write) -> Result<()> {
+        let mut builder = TarBuilder::new(writer);
+        for rel_path in rel_paths {
+            let entry_name = Self::sanitize_rel_path(rel_path)?;
+            let (bytes, size, mtime_secs) = self.read_for_archive(rel_path).await?;
+            let mut header = TarHeader::new_gnu();
+            header.set_size(size);
+            header.set_mtime(mtime_secs);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, &entry_name, bytes.as_slice())?;
+        }
+        builder.finish()?;
+        Ok(())
+    }
+
+    /// 读出单个文件的完整内容及其 size/mtime，供 `export_archive` 填 tar header 用；
+    /// 分块存储的文件复用 `get_manifest_file_info`/`read_chunked`，普通文件复用 `get_file_info`
+    async fn read_for_archive(&self, rel_path: &str) -> Result<(Vec<u8>, u64, u64)> {
+        if let Some(s3) = &self.s3 {
+            let bytes = s3.get(rel_path).await?;
+            let size = bytes.len() as u64;
+            return Ok((bytes, size, Utc::now().timestamp().max(0) as u64));
+        }
+
+        let full = self.safe_join(&Self::sanitize_rel_path(rel_path)?, false)?;
+        let manifest_path = self.manifest_path(&full);
+        if manifest_path.is_file() {
+            let info = self
+                .get_manifest_file_info(&manifest_path)?
+                .ok_or_else(|| anyhow!("manifest missing for {}", rel_path))?;
+            let bytes = self.read_chunked(&manifest_path)?;
+            return Ok((bytes, info.size_bytes, (info.created_at / 1000).max(0) as u64));
+        }
+
+        let info = self
+            .get_file_info(&full)?
+            .ok_or_else(|| anyhow!("file not found: {}", rel_path))?;
+        let bytes = fs::read(&full)?;
+        Ok((bytes, info.size_bytes, (info.created_at / 1000).max(0) as u64))
+    }
+
     /// 保存到 base 根目录（兼容旧接口）
     #[allow(dead_code)]
-    pub fn save_processed_data(&self, data: &ProcessedDataFile) -> Result<String> {
-        self.save_at(None, data)
+    pub async fn save_processed_data(&self, data: &ProcessedDataFile) -> Result<String> {
+        self.save_at(None, data).await
     }
 
-    /// 保存到子目录（相对 base）。返回相对路径："dir/filename" 或 "filename"
-    pub fn save_at(&self, rel_dir: Option<&str>, data: &ProcessedDataFile) -> Result<String> {
+    /// 保存到子目录（相对 base）。本地模式下按块去重存储；backend = "s3" 时上传对象并返回对象 URL。
+    pub async fn save_at(&self, rel_dir: Option<&str>, data: &ProcessedDataFile) -> Result<String> {
+        if let Some(s3) = &self.s3 {
+            let rel = Self::join_rel(rel_dir, &data.filename);
+            s3.put(&rel, data.bytes.clone()).await?;
+            return Ok(s3.object_url(&rel));
+        }
+
         // 1) 目录
         let dir_path = if let Some(d) = rel_dir {
             let safe = Self::sanitize_rel_path(d)?;
@@ -91,31 +703,347 @@ impl FileManager {
             return Err(anyhow!("filename must not contain path separators"));
         }
 
-        // 3) 写入
+        // 3) 分块写入 + 清单
         let full_path = dir_path.join(&fname_safe);
-        fs::write(&full_path, &data.bytes)?;
+        let chunk_hashes = split_chunks(&data.bytes)
+            .into_iter()
+            .map(|chunk| self.write_chunk(chunk))
+            .collect::<Result<Vec<_>>>()?;
+        let manifest = ChunkManifest {
+            chunk_hashes,
+            total_len: data.bytes.len() as u64,
+        };
+        Self::atomic_write(&self.manifest_path(&full_path), &serde_json::to_vec_pretty(&manifest)?)?;
 
         // 4) 返回相对路径
-        let rel = if let Some(d) = rel_dir {
-            let d_trim = d.trim_matches(|c| c == '/' || c == '\\');
-            if d_trim.is_empty() {
-                data.filename.clone()
+        Ok(Self::join_rel(rel_dir, &data.filename))
+    }
+
+    /// 拼出 "dir/filename" 或 "filename" 形式的相对路径
+    fn join_rel(rel_dir: Option<&str>, filename: &str) -> String {
+        match rel_dir {
+            Some(d) => {
+                let d_trim = d.trim_matches(|c| c == '/' || c == '\\');
+                if d_trim.is_empty() {
+                    filename.to_string()
+                } else {
+                    format!("{}/{}", d_trim.replace('\\', "/"), filename)
+                }
+            }
+            None => filename.to_string(),
+        }
+    }
+
+    /// 全局限额清理（仅 base 根目录；如需递归清理可按需扩展）。s3 模式下按对象最后修改时间清理。
+    pub async fn cleanup_old_files(&self, max_files: usize) -> Result<()> {
+        if let Some(s3) = &self.s3 {
+            let mut files = s3.list(None).await?;
+            if files.len() > max_files {
+                for fi in files.drain(max_files..) {
+                    let _ = s3.delete(&fi.filename).await;
+                }
+            }
+            return Ok(());
+        }
+
+        let mut files = self.list_files().await?;
+        if files.len() > max_files {
+            for fi in files.drain(max_files..) {
+                let full = self.base.join(&fi.filename);
+                let manifest_path = self.manifest_path(&full);
+                if manifest_path.is_file() {
+                    let _ = fs::remove_file(&manifest_path);
+                } else {
+                    let _ = fs::remove_file(&full);
+                }
+            }
+            self.gc_unreferenced_chunks()?;
+        }
+        Ok(())
+    }
+
+    /// 按 `policy` 递归清理整棵目录树：先删掉超过 `max_age_ms` 的文件（与数量/大小无关），
+    /// 再从最旧到最新依次删除，直到同时满足 `max_files`/`max_total_bytes`，最后清掉因此
+    /// 变空的子目录。三项约束都为 `None` 时不删除任何文件。s3 模式没有目录层级，按年龄/
+    /// 数量/总字节数同样从最旧开始删，但没有“空目录”可清。
+    pub async fn cleanup(&self, policy: &RetentionPolicy) -> Result<CleanupSummary> {
+        if self.s3.is_some() {
+            return self.cleanup_s3(policy).await;
+        }
+
+        let mut files = self.list_files_recursive(None, usize::MAX, None).await?;
+        let (removed, _) = Self::select_for_removal(&mut files, policy);
+
+        let mut reclaimed_bytes = 0u64;
+        for fi in &removed {
+            let full = self.base.join(&fi.filename);
+            let manifest_path = self.manifest_path(&full);
+            if manifest_path.is_file() {
+                let _ = fs::remove_file(&manifest_path);
             } else {
-                format!("{}/{}", d_trim.replace('\\', "/"), data.filename)
+                let _ = fs::remove_file(&full);
+            }
+            reclaimed_bytes += fi.size_bytes;
+        }
+
+        if !removed.is_empty() {
+            self.gc_unreferenced_chunks()?;
+            self.prune_empty_dirs(&self.base)?;
+        }
+
+        Ok(CleanupSummary { removed_count: removed.len(), reclaimed_bytes })
+    }
+
+    async fn cleanup_s3(&self, policy: &RetentionPolicy) -> Result<CleanupSummary> {
+        let s3 = self.s3.as_ref().expect("cleanup_s3 called without an s3 target");
+        let mut files = s3.list(None).await?;
+        let (removed, _) = Self::select_for_removal(&mut files, policy);
+
+        let mut reclaimed_bytes = 0u64;
+        for fi in &removed {
+            let _ = s3.delete(&fi.filename).await;
+            reclaimed_bytes += fi.size_bytes;
+        }
+
+        Ok(CleanupSummary { removed_count: removed.len(), reclaimed_bytes })
+    }
+
+    /// 从 `files`（会被按创建时间升序重排）中挑出要删除的条目：先挑出超龄的，再从最旧开始
+    /// 挑，直到剩余数量/总字节数满足 `policy`。返回 (待删除条目, 清理后剩余的总字节数)。
+    fn select_for_removal(files: &mut Vec<FileInfo>, policy: &RetentionPolicy) -> (Vec<FileInfo>, u64) {
+        files.sort_by_key(|fi| fi.created_at);
+
+        let now_ms = Utc::now().timestamp_millis();
+        let mut total_bytes: u64 = files.iter().map(|fi| fi.size_bytes).sum();
+        let mut removed: Vec<FileInfo> = Vec::new();
+
+        files.retain(|fi| {
+            let expired = policy.max_age_ms.is_some_and(|max_age| now_ms - fi.created_at > max_age);
+            if expired {
+                removed.push(fi.clone());
+                total_bytes -= fi.size_bytes;
+                false
+            } else {
+                true
+            }
+        });
+
+        while policy.max_files.is_some_and(|m| files.len() > m)
+            || policy.max_total_bytes.is_some_and(|m| total_bytes > m)
+        {
+            if files.is_empty() {
+                break;
+            }
+            let fi = files.remove(0);
+            total_bytes -= fi.size_bytes;
+            removed.push(fi);
+        }
+
+        (removed, total_bytes)
+    }
+
+    /// 递归删除清理后变空的子目录；不删除 base 本身，也跳过块存储目录 `chunks/`
+    fn prune_empty_dirs(&self, dir: &Path) -> Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let chunks_dir = self.chunks_dir();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_dir() || path == chunks_dir {
+                continue;
+            }
+            self.prune_empty_dirs(&path)?;
+            if path != self.base && fs::read_dir(&path)?.next().is_none() {
+                let _ = fs::remove_dir(&path);
+            }
+        }
+        Ok(())
+    }
+
+    /// 当前存储的逻辑字节数 vs. 去重后的物理字节数，用于 `GET /api/files/stats`。
+    /// s3 模式下没有本地去重，physical == logical。
+    pub async fn storage_stats(&self) -> Result<StorageStats> {
+        if let Some(s3) = &self.s3 {
+            let files = s3.list(None).await?;
+            let total: u64 = files.iter().map(|f| f.size_bytes).sum();
+            return Ok(StorageStats {
+                logical_bytes: total,
+                physical_bytes: total,
+                chunk_count: 0,
+                manifest_count: files.len(),
+                dedup_ratio: 1.0,
+            });
+        }
+
+        let mut logical_bytes = 0u64;
+        let mut raw_physical_bytes = 0u64;
+        let mut manifest_count = 0usize;
+        self.accumulate_stats(&self.base, &mut logical_bytes, &mut raw_physical_bytes, &mut manifest_count)?;
+
+        let chunks_dir = self.chunks_dir();
+        let mut chunk_bytes = 0u64;
+        let mut chunk_count = 0usize;
+        if chunks_dir.is_dir() {
+            for entry in fs::read_dir(&chunks_dir)? {
+                let path = entry?.path();
+                if path.is_file() {
+                    chunk_bytes += path.metadata()?.len();
+                    chunk_count += 1;
+                }
             }
+        }
+
+        let physical_bytes = raw_physical_bytes + chunk_bytes;
+        let dedup_ratio = if physical_bytes > 0 {
+            logical_bytes as f64 / physical_bytes as f64
         } else {
-            data.filename.clone()
+            1.0
         };
-        Ok(rel)
+
+        Ok(StorageStats {
+            logical_bytes,
+            physical_bytes,
+            chunk_count,
+            manifest_count,
+            dedup_ratio,
+        })
     }
 
-    /// 全局限额清理（仅 base 根目录；如需递归清理可按需扩展）
-    pub fn cleanup_old_files(&self, max_files: usize) -> Result<()> {
-        let mut files = self.list_files()?;
-        if files.len() > max_files {
-            files.drain(max_files..).for_each(|fi| {
-                let _ = fs::remove_file(self.base.join(fi.filename));
-            });
+    fn chunks_dir(&self) -> PathBuf {
+        self.base.join(CHUNKS_DIR)
+    }
+
+    /// manifest 文件名 = 原始相对路径 + MANIFEST_SUFFIX
+    fn manifest_path(&self, full_path: &Path) -> PathBuf {
+        let mut s = full_path.as_os_str().to_os_string();
+        s.push(MANIFEST_SUFFIX);
+        PathBuf::from(s)
+    }
+
+    /// 写入一个块：按其 BLAKE3 哈希命名，若已存在相同哈希则跳过写入（去重）
+    fn write_chunk(&self, chunk: &[u8]) -> Result<String> {
+        let hash = blake3::hash(chunk).to_hex().to_string();
+        let dir = self.chunks_dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(&hash);
+        if !path.exists() {
+            Self::atomic_write(&path, chunk)?;
+        }
+        Ok(hash)
+    }
+
+    /// 崩溃安全的写入：先把内容写到同目录下的临时文件（`<name>.<随机十六进制>.tmp`）并
+    /// fsync，再用 `fs::rename` 原地替换目标路径。rename 只在同一文件系统内是原子的，
+    /// 所以临时文件必须和目标文件同目录，不能放到系统级 `/tmp`；中途出错时清理掉临时文件，
+    /// 这样并发读者（`read_file`/`list_files_in`）只会看到完整的旧文件或新文件，不会看到半截内容
+    fn atomic_write(path: &Path, bytes: &[u8]) -> Result<()> {
+        let file_name = path.file_name()
+            .ok_or_else(|| anyhow!("Path has no file name: {}", path.display()))?;
+        let tmp_name = format!("{}.{}.tmp", file_name.to_string_lossy(), Self::random_hex_suffix());
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let write_result = (|| -> Result<()> {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(bytes)?;
+            file.sync_all()?;
+            Ok(())
+        })();
+
+        if write_result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+            write_result?;
+        }
+
+        if let Err(e) = fs::rename(&tmp_path, path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    /// 进程 id + 当前时间的纳秒数拼成的十六进制串，只用来避免临时文件名冲突，不要求密码学随机性
+    fn random_hex_suffix() -> String {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        format!("{:x}-{:x}", std::process::id(), nanos)
+    }
+
+    fn read_chunked(&self, manifest_path: &Path) -> Result<Vec<u8>> {
+        let manifest: ChunkManifest = serde_json::from_slice(&fs::read(manifest_path)?)?;
+        let mut out = Vec::with_capacity(manifest.total_len as usize);
+        for hash in &manifest.chunk_hashes {
+            out.extend_from_slice(&fs::read(self.chunks_dir().join(hash))?);
+        }
+        Ok(out)
+    }
+
+    /// 标记-清除：收集仍被引用的块哈希，删除 `chunks/` 下不再被任何 manifest 引用的块
+    fn gc_unreferenced_chunks(&self) -> Result<()> {
+        let chunks_dir = self.chunks_dir();
+        if !chunks_dir.is_dir() {
+            return Ok(());
+        }
+        let mut referenced = HashSet::new();
+        self.collect_manifest_hashes(&self.base, &mut referenced)?;
+
+        for entry in fs::read_dir(&chunks_dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if !referenced.contains(name) {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_manifest_hashes(&self, dir: &Path, out: &mut HashSet<String>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if path.file_name().and_then(|n| n.to_str()) == Some(CHUNKS_DIR) {
+                    continue;
+                }
+                self.collect_manifest_hashes(&path, out)?;
+            } else if path.to_string_lossy().ends_with(MANIFEST_SUFFIX) {
+                if let Ok(manifest) = serde_json::from_slice::<ChunkManifest>(&fs::read(&path)?) {
+                    out.extend(manifest.chunk_hashes);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn accumulate_stats(
+        &self,
+        dir: &Path,
+        logical_bytes: &mut u64,
+        raw_physical_bytes: &mut u64,
+        manifest_count: &mut usize,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if dir == self.base && path.file_name().and_then(|n| n.to_str()) == Some(CHUNKS_DIR) {
+                    continue;
+                }
+                self.accumulate_stats(&path, logical_bytes, raw_physical_bytes, manifest_count)?;
+            } else if path.to_string_lossy().ends_with(MANIFEST_SUFFIX) {
+                if let Ok(manifest) = serde_json::from_slice::<ChunkManifest>(&fs::read(&path)?) {
+                    *logical_bytes += manifest.total_len;
+                    *manifest_count += 1;
+                }
+            } else if let Ok(meta) = path.metadata() {
+                *logical_bytes += meta.len();
+                *raw_physical_bytes += meta.len();
+            }
         }
         Ok(())
     }
@@ -143,7 +1071,7 @@ impl FileManager {
             .to_string_lossy()
             .replace('\\', "/");
 
-        let file_type = Self::determine_file_type(rel.as_str());
+        let file_type = determine_file_type(rel.as_str());
 
         Ok(Some(FileInfo {
             filename: rel,
@@ -153,17 +1081,37 @@ impl FileManager {
         }))
     }
 
-    fn determine_file_type(filename: &str) -> String {
-        let lower = filename.to_ascii_lowercase();
-        if lower.ends_with(".txt") {
-            "raw_frames".to_string()
-        } else if lower.ends_with(".bin") || lower.ends_with(".dat") {
-            "binary".to_string()
-        } else if lower.ends_with(".json") {
-            "json".to_string()
-        } else {
-            "unknown".to_string()
-        }
+    /// 由 manifest 侧车文件推导出逻辑文件的 FileInfo（size 取自清单里的 total_len）
+    fn get_manifest_file_info(&self, manifest_path: &Path) -> Result<Option<FileInfo>> {
+        let manifest: ChunkManifest = match serde_json::from_slice(&fs::read(manifest_path)?) {
+            Ok(m) => m,
+            Err(_) => return Ok(None),
+        };
+        let meta = manifest_path.metadata()?;
+        let created = meta
+            .created()
+            .or_else(|_| meta.modified())
+            .map(|t| {
+                t.duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as i64
+            })
+            .unwrap_or_else(|_| Utc::now().timestamp_millis());
+
+        let full_str = manifest_path.to_string_lossy();
+        let logical_full = PathBuf::from(&full_str[..full_str.len() - MANIFEST_SUFFIX.len()]);
+        let rel = pathdiff::diff_paths(&logical_full, &self.base)
+            .unwrap_or_else(|| PathBuf::from(logical_full.file_name().unwrap_or_default()))
+            .to_string_lossy()
+            .replace('\\', "/");
+        let file_type = determine_file_type(rel.as_str());
+
+        Ok(Some(FileInfo {
+            filename: rel,
+            size_bytes: manifest.total_len,
+            created_at: created,
+            file_type,
+        }))
     }
 
     /// 将相对路径拼到 base 上，并保证**不逃逸出 base**
@@ -179,6 +1127,13 @@ impl FileManager {
         if !canon_check.starts_with(&canon_base) {
             return Err(anyhow!("path escapes base directory"));
         }
+
+        // 上面的检查只看最终（或父级）路径，中间目录若是指向 base 之外的符号链接仍可逃逸，
+        // 且在 canonicalize 之后、实际读写之前文件系统可能发生变化（TOCTOU）。
+        // 逐级审计 rel 的每个组件，拒绝任何已存在且逃逸 base 的符号链接。
+        let mut auditor = PathAuditor::new(&canon_base);
+        auditor.audit(rel)?;
+
         Ok(full)
     }
 
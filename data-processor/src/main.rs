@@ -4,6 +4,13 @@ mod web_server;
 mod websocket;
 mod file_manager;
 mod config;
+mod openapi;
+mod resource_metrics;
+mod metrics;
+mod observability;
+mod telemetry;
+mod transport;
+mod ipc;
 
 use anyhow::Result;
 use std::sync::Arc;
@@ -33,6 +40,7 @@ async fn main() -> Result<()> {
         serial_port: cfg.device.serial_port.clone(),
         socket_address: cfg.device.socket_address.clone(),
         baud_rate: cfg.device.baud_rate,
+        protocol: cfg.device.protocol.clone(),
     };
 
     // 创建设备管理器
@@ -53,17 +61,44 @@ async fn main() -> Result<()> {
     // 用于WebSocket广播触发批次完成事件
     let (trigger_burst_complete_tx, trigger_burst_complete_rx) = tokio::sync::broadcast::channel(100);
 
-    // 创建共享的数据处理器
-    let data_processor = Arc::new(Mutex::new(DataProcessor::new()));
+    // 用于将原始 DeviceEvent 扇出给可选的 MQTT 遥测桥接（不消费，仅旁路抄送）
+    let (device_event_tx, _) = tokio::sync::broadcast::channel::<DeviceEvent>(256);
+
+    // 创建共享的数据处理器（打开持久化的触发批次目录，跨重启保留历史数据）
+    let data_processor = Arc::new(Mutex::new(
+        DataProcessor::new(std::path::Path::new(&cfg.storage.data_dir))?,
+    ));
+
+    // 跨 WebSocket/HTTP 子系统共享的运行时计数器，供 /api/control/info 聚合上报
+    let runtime_metrics = metrics::RuntimeMetrics::new();
+
+    // 数据采集流水线的 Prometheus 指标：包处理、触发批次、IPC 发送失败等，
+    // 与 WebSocket 子系统自己的 ws_metrics 分开统计，在 /metrics 路由里拼接输出
+    let pipeline_metrics = metrics::PipelineMetrics::new();
+
+    // 可选的错误/事件上报子系统（需要 observability.enabled = true 且配置了 dsn 才会实际上报）
+    let observability = observability::Observability::new(&cfg.observability);
+
+    // 协调全部后台任务优雅退出的关闭令牌：收到 Ctrl+C 后置为 true，
+    // 各任务在各自的 select! 间隙里观察到后停止接受新工作并清理收尾
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
     // ======= 设备管理任务 =======
-    let device_handle = tokio::spawn(async move {
+    let shutdown_rx_device = shutdown_rx.clone();
+    let mut device_handle = tokio::spawn(async move {
         loop {
-            match device_manager.run().await {
+            match device_manager.run(shutdown_rx_device.clone()).await {
                 Ok(_) => {
+                    if *shutdown_rx_device.borrow() {
+                        info!("Device manager task exiting for shutdown");
+                        break;
+                    }
                     warn!("Device manager exited normally, restarting...");
                 }
                 Err(e) => {
+                    if *shutdown_rx_device.borrow() {
+                        break;
+                    }
                     error!("Device manager error: {}, restarting in 5s...", e);
                     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
                 }
@@ -77,12 +112,46 @@ async fn main() -> Result<()> {
     let trigger_burst_complete_tx_clone = trigger_burst_complete_tx.clone();
     let pkt_tx_clone = pkt_tx.clone();
     let data_processor_clone = data_processor.clone();
-    
-    let event_handle = tokio::spawn(async move {
+    let device_event_tx_clone = device_event_tx.clone();
+    let pipeline_metrics_clone = pipeline_metrics.clone();
+
+    let mut shutdown_rx_event = shutdown_rx.clone();
+    let mut event_handle = tokio::spawn(async move {
         let mut packet_count = 0u64;
         let mut _current_burst_id: Option<String> = None;
-        
-        while let Some(event) = device_events.recv().await {
+        // 批量读取一次性从 channel 里取到的 DataPacket 时，顺带取出的第一个非
+        // DataPacket 事件先存在这里，下一轮循环当作正常事件处理，而不是丢弃
+        let mut pending_event: Option<DeviceEvent> = None;
+
+        loop {
+        let event = if let Some(ev) = pending_event.take() {
+            ev
+        } else {
+            tokio::select! {
+            maybe_event = device_events.recv() => {
+                match maybe_event {
+                    Some(event) => event,
+                    None => break,
+                }
+            }
+            _ = shutdown_rx_event.changed() => {
+                if !*shutdown_rx_event.borrow() {
+                    continue;
+                }
+                info!("Event processing loop shutting down, finalizing in-flight trigger burst");
+                let completed_burst = {
+                    let mut processor = data_processor_clone.lock().await;
+                    processor.complete_trigger_burst()
+                };
+                if let Some(burst) = completed_burst {
+                    pipeline_metrics_clone.record_trigger_burst_completed(burst.total_samples);
+                    let _ = trigger_burst_complete_tx_clone.send(burst);
+                }
+                break;
+            }
+            }
+        };
+            let _ = device_event_tx_clone.send(event.clone());
             match event {
                 DeviceEvent::Connected(conn_type) => {
                     info!("Device connected: {}", conn_type);
@@ -90,6 +159,7 @@ async fn main() -> Result<()> {
                     // 重置数据处理器状态
                     let mut processor = data_processor_clone.lock().await;
                     processor.reset_trigger_state();
+                    processor.reset_sequence_tracking();
                     _current_burst_id = None;
                 }
                 DeviceEvent::Disconnected => {
@@ -108,6 +178,7 @@ async fn main() -> Result<()> {
                         processor.start_trigger_burst(&trigger_event)
                     };
                     _current_burst_id = Some(burst_id);
+                    pipeline_metrics_clone.record_trigger_burst_started();
                     
                     // 广播触发事件到WebSocket客户端
                     let _ = trigger_event_tx_clone.send(trigger_event);
@@ -115,45 +186,69 @@ async fn main() -> Result<()> {
                 DeviceEvent::DataPacket(packet) => {
                     // 收到数据包表示设备连接正常
                     let _ = device_status_tx.send(true); // 数据活跃时更新状态
-                    
-                    // 处理数据包
-                    let processed_result = {
+
+                    // 一次系统调用/读取往往会在 channel 里攒出不止一个 DataPacket；顺手把
+                    // 当前已经到手的都取出来一起批处理，减少加锁和逐包调度的开销。遇到的第
+                    // 一个非 DataPacket 事件存进 pending_event，留给下一轮循环正常处理，
+                    // 不在这里丢弃或跳过。
+                    const MAX_BATCH_SIZE: usize = 32;
+                    let mut batch = vec![packet];
+                    while batch.len() < MAX_BATCH_SIZE {
+                        match device_events.try_recv() {
+                            Ok(DeviceEvent::DataPacket(p)) => {
+                                let _ = device_event_tx_clone.send(DeviceEvent::DataPacket(p.clone()));
+                                batch.push(p);
+                            }
+                            Ok(other) => {
+                                pending_event = Some(other);
+                                break;
+                            }
+                            Err(_) => break,
+                        }
+                    }
+
+                    // 批量处理数据包
+                    let processed_results = {
                         let mut processor = data_processor_clone.lock().await;
-                        processor.process_packet(&packet)
+                        processor.process_packets(&batch)
                     };
 
-                    match processed_result {
-                        Ok(processed) => {
-                            packet_count += 1;
-                            let _ = pkt_tx_clone.send(packet_count);
-                            
-                            // 日志记录，区分连续和触发数据
-                            let data_len = processed.data.len();
-                            let data_source = processed.data_type.source.clone();
-                            let trigger_info = processed.data_type.trigger_info.clone();
-                            
-                            // 广播处理后的数据
-                            let _ = processed_tx_clone.send(processed);
-                            
-                            match data_source {
-                                crate::data_processing::DataSource::Continuous => {
-                                    if packet_count % 100 == 0 { // 每100包记录一次，避免日志过多
-                                        info!("Processed continuous data packet #{}, {} samples", 
-                                              packet_count, data_len);
+                    for processed_result in processed_results {
+                        match processed_result {
+                            Ok(processed) => {
+                                packet_count += 1;
+                                let _ = pkt_tx_clone.send(packet_count);
+                                pipeline_metrics_clone.record_packet_processed(processed.metadata.processing_time_us);
+
+                                // 日志记录，区分连续和触发数据
+                                let data_len = processed.data.len();
+                                let data_source = processed.data_type.source.clone();
+                                let trigger_info = processed.data_type.trigger_info.clone();
+
+                                // 广播处理后的数据
+                                let _ = processed_tx_clone.send(processed);
+
+                                match data_source {
+                                    crate::data_processing::DataSource::Continuous => {
+                                        if packet_count % 100 == 0 { // 每100包记录一次，避免日志过多
+                                            info!("Processed continuous data packet #{}, {} samples",
+                                                  packet_count, data_len);
+                                        }
                                     }
-                                }
-                                crate::data_processing::DataSource::Trigger => {
-                                    if let Some(ref trigger_info) = trigger_info {
-                                        info!("Processed trigger data packet #{}, sequence in burst: {}, {} samples", 
-                                              packet_count, 
-                                              trigger_info.sequence_in_burst.unwrap_or(0), 
-                                              data_len);
+                                    crate::data_processing::DataSource::Trigger => {
+                                        if let Some(ref trigger_info) = trigger_info {
+                                            info!("Processed trigger data packet #{}, sequence in burst: {}, {} samples",
+                                                  packet_count,
+                                                  trigger_info.sequence_in_burst.unwrap_or(0),
+                                                  data_len);
+                                        }
                                     }
                                 }
                             }
-                        }
-                        Err(e) => {
-                            error!("Failed to process data packet: {}", e);
+                            Err(e) => {
+                                pipeline_metrics_clone.record_parse_error();
+                                error!("Failed to process data packet: {}", e);
+                            }
                         }
                     }
                 }
@@ -171,10 +266,11 @@ async fn main() -> Result<()> {
                             let processor = data_processor_clone.lock().await;
                             processor.get_stats()
                         };
-                        
-                        info!("Trigger burst completed: id={}, packets={}, samples={}", 
+
+                        info!("Trigger burst completed: id={}, packets={}, samples={}",
                               burst.burst_id, burst.data_packets.len(), burst.total_samples);
-                        
+                        pipeline_metrics_clone.record_trigger_burst_completed(burst.total_samples);
+
                         // 广播触发批次完成事件到WebSocket客户端
                         let _ = trigger_burst_complete_tx_clone.send(burst);
                         
@@ -205,9 +301,12 @@ async fn main() -> Result<()> {
                 }
                 DeviceEvent::FrameReceived(frame) => {
                     // 只在调试模式下记录帧信息，避免日志过多
-                    tracing::debug!("Device frame: cmd=0x{:02X}, seq={}, len={}", 
+                    tracing::debug!("Device frame: cmd=0x{:02X}, seq={}, len={}",
                                    frame.command_id, frame.sequence, frame.payload.len());
                 }
+                DeviceEvent::DownloadProgress { blob_type, sent, total } => {
+                    info!("Blob download progress: type={}, {}/{} bytes", blob_type, sent, total);
+                }
             }
         }
         warn!("Device event processing loop ended");
@@ -215,18 +314,58 @@ async fn main() -> Result<()> {
 
     // ======= WebSocket 服务：广播处理后的数据、触发事件和批次完成事件 =======
     let mut ws_server = websocket::WebSocketServer::new(
-        cfg.websocket.clone(), 
+        cfg.websocket.clone(),
         processed_rx_for_ws,
         trigger_event_rx,
-        trigger_burst_complete_rx
+        trigger_burst_complete_rx,
+        runtime_metrics.clone(),
     );
     let ws_clients_rx = ws_server.client_count_rx.clone();
-    let ws_handle = tokio::spawn(async move {
-        if let Err(e) = ws_server.run().await {
+    let ws_metrics = ws_server.ws_metrics.clone();
+    let shutdown_rx_ws = shutdown_rx.clone();
+    let mut ws_handle = tokio::spawn(async move {
+        if let Err(e) = ws_server.run(shutdown_rx_ws).await {
             error!("WebSocket server error: {}", e);
         }
     });
 
+    // ======= 可选的 MQTT 遥测桥接：设备事件 -> broker，broker -> 设备命令 =======
+    if cfg.mqtt.enabled {
+        let mut telemetry_bridge = telemetry::TelemetryBridge::new(
+            cfg.mqtt.clone(),
+            device_event_tx.subscribe(),
+            device_command_tx.clone(),
+        );
+        tokio::spawn(async move {
+            if let Err(e) = telemetry_bridge.run().await {
+                error!("MQTT telemetry bridge error: {}", e);
+            }
+        });
+    }
+
+    // ======= 可选的共享内存 IPC 数据通路：控制通道（序号缺口重传请求等）+
+    // data-reader 前端进程写入的共享内存环形缓冲区 =======
+    if cfg.ipc.enabled {
+        let codec_kind = match cfg.ipc.codec.as_str() {
+            "length_prefixed" => ipc::IpcCodecKind::LengthPrefixed,
+            "msgpack" => ipc::IpcCodecKind::MsgPack,
+            _ => ipc::IpcCodecKind::JsonLines,
+        };
+        match ipc::IpcClient::start_with_codec(&cfg.ipc.pipe_name, codec_kind) {
+            Ok(ipc_client) => {
+                let mut ipc_bridge = ipc::IpcBridge::new(cfg.ipc.clone(), ipc_client);
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = ipc_bridge.run() {
+                        error!("Shared memory IPC bridge stopped: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                error!("Failed to start IPC control channel on '{}': {}", cfg.ipc.pipe_name, e);
+            }
+        }
+    }
+
     // ======= Web API（Axum）=======
     let web = web_server::WebServer::new(
         cfg.clone(),
@@ -235,9 +374,17 @@ async fn main() -> Result<()> {
         ws_clients_rx.clone(),
         data_processor.clone(),
         device_status_rx,
+        processed_tx.clone(),
+        trigger_event_tx.clone(),
+        trigger_burst_complete_tx.clone(),
+        runtime_metrics,
+        observability,
+        ws_metrics,
+        pipeline_metrics,
     );
-    let http_handle = tokio::spawn(async move {
-        if let Err(e) = web.run().await {
+    let shutdown_rx_http = shutdown_rx.clone();
+    let mut http_handle = tokio::spawn(async move {
+        if let Err(e) = web.run(shutdown_rx_http).await {
             error!("Web server error: {}", e);
         }
     });
@@ -255,16 +402,16 @@ async fn main() -> Result<()> {
 
     // 等待任一任务退出或 Ctrl+C
     tokio::select! {
-        _ = device_handle => {
+        _ = &mut device_handle => {
             error!("Device manager terminated");
         }
-        _ = event_handle => {
+        _ = &mut event_handle => {
             error!("Event processing task terminated");
         }
-        _ = ws_handle => {
+        _ = &mut ws_handle => {
             error!("WebSocket server terminated");
         }
-        _ = http_handle => {
+        _ = &mut http_handle => {
             error!("Web server terminated");
         }
         _ = tokio::signal::ctrl_c() => {
@@ -273,7 +420,25 @@ async fn main() -> Result<()> {
     }
 
     info!("Shutting down gracefully...");
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    // 通知所有任务开始收尾；再分别给每个任务一个有限的时间窗口,超时就不再等待，
+    // 避免某个任务卡住导致进程无法退出
+    let _ = shutdown_tx.send(true);
+
+    let shutdown_timeout = tokio::time::Duration::from_secs(5);
+    for (name, handle) in [
+        ("device manager", &mut device_handle),
+        ("event processing", &mut event_handle),
+        ("websocket server", &mut ws_handle),
+        ("web server", &mut http_handle),
+    ] {
+        if !handle.is_finished() {
+            match tokio::time::timeout(shutdown_timeout, handle).await {
+                Ok(_) => info!("{} task shut down cleanly", name),
+                Err(_) => warn!("{} task did not shut down within {:?}, abandoning it", name, shutdown_timeout),
+            }
+        }
+    }
+
     info!("Shutdown complete");
     Ok(())
 }
\ No newline at end of file
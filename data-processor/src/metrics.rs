@@ -0,0 +1,231 @@
+//! 跨 HTTP/WebSocket 两个子系统共享的运行时计数器，为 `/api/control/info`
+//! 的聚合状态报告（类似 Redis `INFO`）提供数据来源。`RuntimeMetrics` 克隆后
+//! 仍指向同一份底层计数器，与仓库里其它共享状态句柄（如 `ConfigController`）的用法一致。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct LastSave {
+    pub path: String,
+    pub timestamp: i64,
+}
+
+struct Inner {
+    bytes_written: AtomicU64,
+    files_saved: AtomicU64,
+    ws_messages_sent: AtomicU64,
+    pending_writes: AtomicU64,
+    peak_rss_mb_bits: AtomicU64,
+    last_save: Mutex<Option<LastSave>>,
+}
+
+#[derive(Clone)]
+pub struct RuntimeMetrics(Arc<Inner>);
+
+impl RuntimeMetrics {
+    pub fn new() -> Self {
+        Self(Arc::new(Inner {
+            bytes_written: AtomicU64::new(0),
+            files_saved: AtomicU64::new(0),
+            ws_messages_sent: AtomicU64::new(0),
+            pending_writes: AtomicU64::new(0),
+            peak_rss_mb_bits: AtomicU64::new(0),
+            last_save: Mutex::new(None),
+        }))
+    }
+
+    /// 在发起一次落盘写入前调用，配合 [`Self::end_write`] 追踪正在进行的写入数
+    pub fn begin_write(&self) {
+        self.0.pending_writes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 写入结束（无论成功与否）时调用；成功时记下字节数与保存路径
+    pub fn end_write(&self, saved: Option<(&str, usize)>) {
+        self.0.pending_writes.fetch_sub(1, Ordering::Relaxed);
+        if let Some((path, bytes)) = saved {
+            self.0.bytes_written.fetch_add(bytes as u64, Ordering::Relaxed);
+            self.0.files_saved.fetch_add(1, Ordering::Relaxed);
+            *self.0.last_save.lock().unwrap() = Some(LastSave {
+                path: path.to_string(),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+    }
+
+    pub fn record_ws_messages_sent(&self, count: u64) {
+        if count > 0 {
+            self.0.ws_messages_sent.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// 记录一次 RSS 采样，内部维护到目前为止见过的峰值
+    pub fn note_rss_sample(&self, rss_mb: f64) {
+        let bits = rss_mb.to_bits();
+        let mut current = self.0.peak_rss_mb_bits.load(Ordering::Relaxed);
+        while f64::from_bits(current) < rss_mb {
+            match self.0.peak_rss_mb_bits.compare_exchange_weak(
+                current, bits, Ordering::Relaxed, Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            bytes_written: self.0.bytes_written.load(Ordering::Relaxed),
+            files_saved: self.0.files_saved.load(Ordering::Relaxed),
+            ws_messages_sent: self.0.ws_messages_sent.load(Ordering::Relaxed),
+            pending_writes: self.0.pending_writes.load(Ordering::Relaxed),
+            peak_rss_mb: f64::from_bits(self.0.peak_rss_mb_bits.load(Ordering::Relaxed)),
+            last_save: self.0.last_save.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl Default for RuntimeMetrics {
+    fn default() -> Self { Self::new() }
+}
+
+pub struct MetricsSnapshot {
+    pub bytes_written: u64,
+    pub files_saved: u64,
+    pub ws_messages_sent: u64,
+    pub pending_writes: u64,
+    pub peak_rss_mb: f64,
+    pub last_save: Option<LastSave>,
+}
+
+/// 数据采集流水线（设备读取 -> 解析 -> 触发批次 -> IPC）的 Prometheus 指标，
+/// 与 [`crate::websocket::WsMetrics`] 各管一块、各自持有独立的 `Registry`，
+/// 在 `/metrics` 路由里分别编码后拼接返回。克隆后仍指向同一份底层 `Registry`，
+/// 用法与 `RuntimeMetrics`/`WsMetrics` 一致。
+#[derive(Clone)]
+pub struct PipelineMetrics {
+    registry: prometheus::Registry,
+    packets_processed_total: prometheus::IntCounter,
+    packets_dropped_total: prometheus::IntCounter,
+    trigger_bursts_started_total: prometheus::IntCounter,
+    trigger_bursts_completed_total: prometheus::IntCounter,
+    ipc_send_failures_total: prometheus::IntCounter,
+    parse_errors_total: prometheus::IntCounter,
+    shared_memory_ring_lag: prometheus::IntGauge,
+    packet_processing_duration_seconds: prometheus::Histogram,
+    trigger_burst_samples: prometheus::Histogram,
+}
+
+impl PipelineMetrics {
+    pub fn new() -> Self {
+        let registry = prometheus::Registry::new();
+
+        let packets_processed_total = prometheus::IntCounter::new(
+            "pipeline_packets_processed_total", "成功解析并处理的数据包总数",
+        ).unwrap();
+        let packets_dropped_total = prometheus::IntCounter::new(
+            "pipeline_packets_dropped_total", "因环形缓冲区套圈或槽位被覆盖而丢弃的包总数",
+        ).unwrap();
+        let trigger_bursts_started_total = prometheus::IntCounter::new(
+            "pipeline_trigger_bursts_started_total", "开始的触发批次总数",
+        ).unwrap();
+        let trigger_bursts_completed_total = prometheus::IntCounter::new(
+            "pipeline_trigger_bursts_completed_total", "完成的触发批次总数",
+        ).unwrap();
+        let ipc_send_failures_total = prometheus::IntCounter::new(
+            "pipeline_ipc_send_failures_total", "IPC 控制通道发送失败次数",
+        ).unwrap();
+        let parse_errors_total = prometheus::IntCounter::new(
+            "pipeline_parse_errors_total", "数据包解析失败次数（长度不匹配、通道掩码为空等）",
+        ).unwrap();
+        let shared_memory_ring_lag = prometheus::IntGauge::new(
+            "pipeline_shared_memory_ring_lag", "共享内存环形缓冲区当前的生产者-消费者滞后（write_index - read_index）",
+        ).unwrap();
+        let packet_processing_duration_seconds = prometheus::Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "pipeline_packet_processing_duration_seconds", "单个数据包的解析处理耗时（批量处理时为批次内的均摊值）",
+            ),
+        ).unwrap();
+        let trigger_burst_samples = prometheus::Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "pipeline_trigger_burst_samples", "每个触发批次累计的样本数",
+            ).buckets(prometheus::exponential_buckets(64.0, 2.0, 12).unwrap()),
+        ).unwrap();
+
+        registry.register(Box::new(packets_processed_total.clone())).unwrap();
+        registry.register(Box::new(packets_dropped_total.clone())).unwrap();
+        registry.register(Box::new(trigger_bursts_started_total.clone())).unwrap();
+        registry.register(Box::new(trigger_bursts_completed_total.clone())).unwrap();
+        registry.register(Box::new(ipc_send_failures_total.clone())).unwrap();
+        registry.register(Box::new(parse_errors_total.clone())).unwrap();
+        registry.register(Box::new(shared_memory_ring_lag.clone())).unwrap();
+        registry.register(Box::new(packet_processing_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(trigger_burst_samples.clone())).unwrap();
+
+        Self {
+            registry,
+            packets_processed_total,
+            packets_dropped_total,
+            trigger_bursts_started_total,
+            trigger_bursts_completed_total,
+            ipc_send_failures_total,
+            parse_errors_total,
+            shared_memory_ring_lag,
+            packet_processing_duration_seconds,
+            trigger_burst_samples,
+        }
+    }
+
+    /// 记录一个数据包处理完成，`processing_time_us` 是处理耗时（微秒）
+    pub fn record_packet_processed(&self, processing_time_us: u64) {
+        self.packets_processed_total.inc();
+        self.packet_processing_duration_seconds
+            .observe(processing_time_us as f64 / 1_000_000.0);
+    }
+
+    /// 记录因环形缓冲区套圈/槽位覆盖而丢弃的包数
+    pub fn record_packets_dropped(&self, count: u64) {
+        if count > 0 {
+            self.packets_dropped_total.inc_by(count);
+        }
+    }
+
+    pub fn record_trigger_burst_started(&self) {
+        self.trigger_bursts_started_total.inc();
+    }
+
+    /// 记录一个触发批次完成，`sample_count` 是该批次累计的样本数
+    pub fn record_trigger_burst_completed(&self, sample_count: usize) {
+        self.trigger_bursts_completed_total.inc();
+        self.trigger_burst_samples.observe(sample_count as f64);
+    }
+
+    pub fn record_ipc_send_failure(&self) {
+        self.ipc_send_failures_total.inc();
+    }
+
+    pub fn record_parse_error(&self) {
+        self.parse_errors_total.inc();
+    }
+
+    /// 更新共享内存环形缓冲区当前的生产者-消费者滞后量
+    pub fn set_shared_memory_ring_lag(&self, lag: i64) {
+        self.shared_memory_ring_lag.set(lag);
+    }
+
+    /// text_encode 当前的指标快照，供 `/metrics` 路由直接返回
+    pub fn encode(&self) -> String {
+        use prometheus::Encoder;
+        let encoder = prometheus::TextEncoder::new();
+        let metric_families = self.registry.gather();
+        let mut buf = Vec::new();
+        encoder.encode(&metric_families, &mut buf).unwrap_or_default();
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+impl Default for PipelineMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
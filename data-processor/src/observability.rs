@@ -0,0 +1,147 @@
+//! 可选的错误/事件上报子系统：捕获 handler 返回的错误状态与 panic，附带请求上下文
+//! （接口路径、burst/file id、客户端 IP、脱敏后的负载大小）与最近的生命周期面包屑，
+//! 按采样率上报到配置的 DSN（兼容 Sentry Store API 的简单 JSON POST）。
+//! `observability.enabled = false` 或未配置 `dsn` 时整个子系统是空操作。
+
+use crate::config::ObservabilityConfig;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// 保留的面包屑条数上限
+const MAX_BREADCRUMBS: usize = 20;
+
+/// 捕获一次错误/panic 时附带的请求上下文
+#[derive(Debug, Clone, Default)]
+pub struct ErrorContext {
+    pub endpoint: String,
+    pub burst_or_file_id: Option<String>,
+    pub client_ip: Option<String>,
+    /// 脱敏后的负载大小（仅字节数，不包含payload本身）
+    pub payload_bytes: Option<u64>,
+}
+
+struct Inner {
+    enabled: bool,
+    dsn: Option<String>,
+    environment: String,
+    sample_rate: f64,
+    client: reqwest::Client,
+    /// 当前采集模式（continuous/trigger），随事件一起上报
+    mode: Mutex<String>,
+    breadcrumbs: Mutex<VecDeque<(i64, String)>>,
+    event_seq: AtomicU64,
+}
+
+/// 跨 WebSocket/HTTP 子系统共享的可观测性句柄。克隆后仍指向同一份底层状态。
+#[derive(Clone)]
+pub struct Observability(Arc<Inner>);
+
+impl Observability {
+    pub fn new(cfg: &ObservabilityConfig) -> Self {
+        Self(Arc::new(Inner {
+            enabled: cfg.enabled && cfg.dsn.is_some(),
+            dsn: cfg.dsn.clone(),
+            environment: cfg.environment.clone(),
+            sample_rate: cfg.sample_rate.clamp(0.0, 1.0),
+            client: reqwest::Client::new(),
+            mode: Mutex::new("continuous".to_string()),
+            breadcrumbs: Mutex::new(VecDeque::with_capacity(MAX_BREADCRUMBS)),
+            event_seq: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled
+    }
+
+    /// 更新当前采集模式（"continuous" / "trigger"），用于标记后续上报的事件
+    pub fn set_mode(&self, mode: &str) {
+        if !self.0.enabled {
+            return;
+        }
+        *self.0.mode.lock().unwrap() = mode.to_string();
+    }
+
+    /// 记录一次生命周期面包屑（start/stop/configure 等），超过上限时丢弃最早的一条
+    pub fn breadcrumb(&self, message: impl Into<String>) {
+        if !self.0.enabled {
+            return;
+        }
+        let mut crumbs = self.0.breadcrumbs.lock().unwrap();
+        if crumbs.len() >= MAX_BREADCRUMBS {
+            crumbs.pop_front();
+        }
+        crumbs.push_back((chrono::Utc::now().timestamp_millis(), message.into()));
+    }
+
+    /// 捕获一次 handler 错误或 panic；按配置的采样率决定是否实际上报，失败只记一条 warn 日志
+    pub fn capture_error(&self, message: impl Into<String>, ctx: ErrorContext) {
+        if !self.0.enabled {
+            return;
+        }
+        let seq = self.0.event_seq.fetch_add(1, Ordering::Relaxed);
+        if !sampled(seq, self.0.sample_rate) {
+            return;
+        }
+        let dsn = match &self.0.dsn {
+            Some(d) => d.clone(),
+            None => return,
+        };
+
+        let message = message.into();
+        let environment = self.0.environment.clone();
+        let mode = self.0.mode.lock().unwrap().clone();
+        let breadcrumbs: Vec<Value> = self
+            .0
+            .breadcrumbs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(ts, msg)| json!({ "timestamp": ts, "message": msg }))
+            .collect();
+        let client = self.0.client.clone();
+
+        let event = json!({
+            "message": message,
+            "environment": environment,
+            "mode": mode,
+            "timestamp": chrono::Utc::now().timestamp_millis(),
+            "request": {
+                "endpoint": ctx.endpoint,
+                "burst_or_file_id": ctx.burst_or_file_id,
+                "client_ip": ctx.client_ip,
+                "payload_bytes": ctx.payload_bytes,
+            },
+            "breadcrumbs": breadcrumbs,
+        });
+
+        // 尽力上报，不影响请求本身的处理
+        tokio::spawn(async move {
+            if let Err(e) = client.post(&dsn).json(&event).send().await {
+                warn!("observability: failed to report event to sink: {}", e);
+            }
+        });
+    }
+}
+
+/// 伪随机的采样决策：混合事件序号与当前时钟纳秒，避免引入额外的随机数依赖
+fn sampled(seq: u64, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    let mut z = seq.wrapping_mul(0x9E3779B97F4A7C15) ^ nanos;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    (z as f64 / u64::MAX as f64) < rate
+}
@@ -0,0 +1,74 @@
+//! 根据现有的请求/响应结构体生成 OpenAPI 3.0 文档（`/api/openapi.json`）。
+//! 路径与结构体保持同步：新增/修改接口时，在 `web_server` 里给处理函数加上
+//! `#[utoipa::path(...)]`，再把它加入下面的 `paths(...)` 列表即可。
+
+use crate::config::{Config, ConfigUpdate, DeviceConfigUpdate, MqttConfig, ObservabilityConfig, StorageConfigUpdate};
+use crate::data_processing::{TriggerBurst, TriggerListPage, TriggerSummary};
+use crate::file_manager::{FileInfo, StorageStats};
+use crate::web_server::{
+    ApiResponseConfig, ApiResponseFiles, ApiResponseSaveTrigger, ApiResponseStatus,
+    ApiResponseStatusReport, ApiResponseStorageStats, ApiResponseString, ApiResponseTriggerList,
+    ConfigureRequest, ControlCommand, SaveTriggerRequest, SaveTriggerResponse, StatusReport,
+    SystemStatus,
+};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Integrated Data Processor API",
+        version = "2.0",
+        description = "HTTP control, trigger-burst management and file management surface for the recorder backend",
+    ),
+    paths(
+        crate::web_server::start_collection,
+        crate::web_server::stop_collection,
+        crate::web_server::get_status,
+        crate::web_server::get_status_info,
+        crate::web_server::list_trigger_bursts,
+        crate::web_server::preview_trigger_burst,
+        crate::web_server::save_trigger_burst,
+        crate::web_server::delete_trigger_burst,
+        crate::web_server::list_files,
+        crate::web_server::get_storage_stats,
+        crate::web_server::download_file,
+        crate::web_server::save_waveform,
+        crate::web_server::get_config,
+        crate::web_server::update_config,
+        crate::web_server::health_check,
+    ),
+    components(schemas(
+        ApiResponseString,
+        ApiResponseStatus,
+        ApiResponseSaveTrigger,
+        ApiResponseFiles,
+        ApiResponseConfig,
+        ApiResponseStorageStats,
+        ApiResponseTriggerList,
+        TriggerListPage,
+        ApiResponseStatusReport,
+        StatusReport,
+        StorageStats,
+        ControlCommand,
+        SystemStatus,
+        ConfigureRequest,
+        SaveTriggerRequest,
+        SaveTriggerResponse,
+        TriggerSummary,
+        TriggerBurst,
+        FileInfo,
+        Config,
+        ConfigUpdate,
+        DeviceConfigUpdate,
+        StorageConfigUpdate,
+        ObservabilityConfig,
+        MqttConfig,
+    )),
+    tags(
+        (name = "control", description = "Device start/stop/mode control"),
+        (name = "trigger", description = "Trigger-burst capture management"),
+        (name = "files", description = "Saved waveform file management"),
+        (name = "config", description = "Runtime configuration inspection and updates"),
+    ),
+)]
+pub struct ApiDoc;
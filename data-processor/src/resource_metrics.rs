@@ -0,0 +1,118 @@
+//! 采集当前进程的内存占用与 CPU 占用率，供 `/health` 和 `SystemStatus.memory_usage_mb` 使用。
+//!
+//! 直接解析 `/proc`（Linux）或调用 `psapi`（Windows），不引入 `sysinfo` 这类全量扫描型依赖，
+//! 避免在每次状态查询的热路径上付出额外开销。
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// `/health` 里 `resources` 字段的内容
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, utoipa::ToSchema)]
+pub struct ProcessResources {
+    pub rss_mb: f64,
+    /// 两次采样之间 `utime+stime` 的增量除以墙钟时间；首次调用没有基准样本，恒为 0
+    pub cpu_percent: f64,
+}
+
+pub fn sample() -> ProcessResources {
+    ProcessResources {
+        rss_mb: rss_mb(),
+        cpu_percent: cpu_percent(),
+    }
+}
+
+pub fn rss_mb() -> f64 {
+    #[cfg(target_os = "linux")]
+    { linux::rss_mb() }
+    #[cfg(target_os = "windows")]
+    { windows::rss_mb() }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    { 0.0 }
+}
+
+fn cpu_percent() -> f64 {
+    #[cfg(target_os = "linux")]
+    {
+        let Some(total_jiffies) = linux::total_cpu_jiffies() else { return 0.0; };
+        // 绝大多数 Linux 发行版的 sysconf(_SC_CLK_TCK) 都是 100
+        const CLK_TCK: f64 = 100.0;
+        let now = Instant::now();
+
+        static LAST_SAMPLE: OnceLock<Mutex<Option<(Instant, u64)>>> = OnceLock::new();
+        let mut guard = LAST_SAMPLE.get_or_init(|| Mutex::new(None)).lock().unwrap();
+
+        let percent = match *guard {
+            Some((prev_time, prev_jiffies)) => {
+                let elapsed = now.duration_since(prev_time).as_secs_f64();
+                let delta_jiffies = total_jiffies.saturating_sub(prev_jiffies) as f64;
+                if elapsed > 0.0 { (delta_jiffies / CLK_TCK) / elapsed * 100.0 } else { 0.0 }
+            }
+            None => 0.0,
+        };
+        *guard = Some((now, total_jiffies));
+        percent
+    }
+    #[cfg(not(target_os = "linux"))]
+    { 0.0 }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs;
+
+    /// 优先解析 `/proc/self/status` 的 `VmRSS:` 行（单位 kB），失败时退回
+    /// `/proc/self/statm` 的第 2 个字段（常驻页数 × 页大小）
+    pub fn rss_mb() -> f64 {
+        if let Ok(status) = fs::read_to_string("/proc/self/status") {
+            for line in status.lines() {
+                if let Some(rest) = line.strip_prefix("VmRSS:") {
+                    if let Some(kb) = rest.trim().split_whitespace().next().and_then(|v| v.parse::<f64>().ok()) {
+                        return kb / 1024.0;
+                    }
+                }
+            }
+        }
+
+        if let Ok(statm) = fs::read_to_string("/proc/self/statm") {
+            if let Some(pages) = statm.split_whitespace().nth(1).and_then(|v| v.parse::<f64>().ok()) {
+                const PAGE_SIZE_KB: f64 = 4.0;
+                return pages * PAGE_SIZE_KB / 1024.0;
+            }
+        }
+
+        0.0
+    }
+
+    /// `/proc/self/stat` 的 utime（字段 14）+ stime（字段 15），单位是 clock ticks。
+    /// `comm` 字段可能包含空格或括号，因此从最后一个 `)` 之后再按空白切分。
+    pub fn total_cpu_jiffies() -> Option<u64> {
+        let stat = fs::read_to_string("/proc/self/stat").ok()?;
+        let after_comm = stat.rsplit_once(')')?.1;
+        let fields: Vec<&str> = after_comm.split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        Some(utime + stime)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use windows_sys::Win32::System::ProcessStatus::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    pub fn rss_mb() -> f64 {
+        unsafe {
+            let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+            let ok = K32GetProcessMemoryInfo(
+                GetCurrentProcess(),
+                &mut counters,
+                std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+            );
+            if ok != 0 {
+                counters.WorkingSetSize as f64 / (1024.0 * 1024.0)
+            } else {
+                0.0
+            }
+        }
+    }
+}
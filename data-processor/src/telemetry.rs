@@ -0,0 +1,113 @@
+//! 将 `DeviceManager` 的事件/命令通道桥接到 MQTT broker：订阅设备事件广播，发布到结构化主题
+//! `recorder/<device_id>/{data,status,trigger,log}`；订阅 `recorder/<device_id>/cmd`，把收到的
+//! JSON 负载反序列化为 `DeviceCommand` 转发给设备管理器。`mqtt.enabled = false` 时调用方不应启动本模块。
+
+use crate::config::MqttConfig;
+use crate::device_communication::{DeviceCommand, DeviceEvent};
+use anyhow::Result;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, warn};
+
+pub struct TelemetryBridge {
+    config: MqttConfig,
+    events: broadcast::Receiver<DeviceEvent>,
+    command_tx: mpsc::UnboundedSender<DeviceCommand>,
+}
+
+impl TelemetryBridge {
+    pub fn new(
+        config: MqttConfig,
+        events: broadcast::Receiver<DeviceEvent>,
+        command_tx: mpsc::UnboundedSender<DeviceCommand>,
+    ) -> Self {
+        Self { config, events, command_tx }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut options = MqttOptions::new(
+            format!("recorder-{}", self.config.device_id),
+            self.config.broker_host.clone(),
+            self.config.broker_port,
+        );
+        options.set_keep_alive(Duration::from_secs(30));
+
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+        let qos = qos_from(self.config.qos);
+
+        let cmd_topic = format!("recorder/{}/cmd", self.config.device_id);
+        client.subscribe(&cmd_topic, qos).await?;
+
+        // 事件发布 task：把设备事件广播逐条转换为结构化 JSON，发布到对应主题
+        let publish_client = client.clone();
+        let device_id = self.config.device_id.clone();
+        let mut events_rx = self.events.resubscribe();
+        tokio::spawn(async move {
+            while let Ok(event) = events_rx.recv().await {
+                if let Some((topic, payload)) = topic_and_payload(&device_id, &event) {
+                    match serde_json::to_vec(&payload) {
+                        Ok(bytes) => {
+                            if let Err(e) = publish_client.publish(&topic, qos, false, bytes).await {
+                                warn!("telemetry: publish to {} failed: {}", topic, e);
+                            }
+                        }
+                        Err(e) => error!("telemetry: failed to serialize event for {}: {}", topic, e),
+                    }
+                }
+            }
+        });
+
+        // 命令订阅：阻塞轮询 MQTT 事件循环，收到 cmd 主题的消息后转发给设备管理器
+        let command_tx = self.command_tx.clone();
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::Publish(p))) if p.topic == cmd_topic => {
+                    match serde_json::from_slice::<DeviceCommand>(&p.payload) {
+                        Ok(cmd) => {
+                            debug!("telemetry: forwarding command from MQTT: {:?}", cmd);
+                            let _ = command_tx.send(cmd);
+                        }
+                        Err(e) => warn!("telemetry: failed to decode command payload on {}: {}", cmd_topic, e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("telemetry: MQTT event loop error: {}, retrying in 2s", e);
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+            }
+        }
+    }
+}
+
+fn qos_from(level: u8) -> QoS {
+    match level {
+        0 => QoS::AtMostOnce,
+        2 => QoS::ExactlyOnce,
+        _ => QoS::AtLeastOnce,
+    }
+}
+
+/// 根据事件类型选择目标主题与 JSON 负载；未纳入遥测范围的事件（连接状态、原始帧等）返回 `None`
+fn topic_and_payload(device_id: &str, event: &DeviceEvent) -> Option<(String, serde_json::Value)> {
+    match event {
+        DeviceEvent::DataPacket(pkt) => Some((
+            format!("recorder/{device_id}/data"),
+            json!({
+                "timestamp_ms": pkt.timestamp_ms,
+                "enabled_channels": pkt.enabled_channels,
+                "sample_count": pkt.sample_count,
+                "data": pkt.sensor_data,
+            }),
+        )),
+        DeviceEvent::StatusUpdate(status) => Some((format!("recorder/{device_id}/status"), json!(status))),
+        DeviceEvent::TriggerEvent(trigger) => Some((format!("recorder/{device_id}/trigger"), json!(trigger))),
+        DeviceEvent::LogMessage { level, message } => Some((
+            format!("recorder/{device_id}/log"),
+            json!({ "level": level, "message": message }),
+        )),
+        _ => None,
+    }
+}
@@ -0,0 +1,186 @@
+//! 生产者↔后端控制通道的跨平台传输层。`IpcClient`（见 [`crate::ipc`]）目前仍直接
+//! 用 `std::fs::OpenOptions` 打开 Windows 命名管道；这里把"连接 + 读字节流 +
+//! 写字节流"抽成一个 `Transport` trait，给 Linux/macOS 下用 Unix domain socket
+//! 做同样的事提供落脚点，Unix 侧额外支持通过 `SCM_RIGHTS` 把共享内存 fd 随控制
+//! 消息一起递交给对端，对应 [`crate::ipc::UnixSharedMemoryReader`]。
+
+use anyhow::{anyhow, Result};
+
+/// 控制通道的连接 + 字节流读写原语。Windows 实现基于命名管道，Unix 实现基于
+/// Unix domain socket；上层（帧编解码、消息分发）不关心具体传输介质。
+pub trait Transport: Send {
+    /// 从传输层读取至多 `buf.len()` 字节，返回实际读到的字节数；0 表示对端已关闭
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+    /// 把 `data` 完整写入传输层
+    fn write_all(&mut self, data: &[u8]) -> Result<()>;
+}
+
+#[cfg(windows)]
+pub use windows_impl::NamedPipeTransport;
+
+#[cfg(windows)]
+mod windows_impl {
+    use super::{Result, Transport};
+    use anyhow::anyhow;
+    use std::fs::{File, OpenOptions};
+    use std::io::{Read, Write};
+
+    /// Windows 命名管道传输：与 `IpcClient` 现有实现一致，分别以只写/只读模式
+    /// 打开同一个管道名得到一对单向句柄
+    pub struct NamedPipeTransport {
+        read: File,
+        write: File,
+    }
+
+    impl NamedPipeTransport {
+        pub fn connect(pipe_name: &str) -> Result<Self> {
+            let write = OpenOptions::new()
+                .write(true)
+                .read(false)
+                .open(pipe_name)
+                .map_err(|e| anyhow!("open named pipe '{}' for write failed: {}", pipe_name, e))?;
+            let read = OpenOptions::new()
+                .read(true)
+                .write(false)
+                .open(pipe_name)
+                .map_err(|e| anyhow!("open named pipe '{}' for read failed: {}", pipe_name, e))?;
+            Ok(Self { read, write })
+        }
+    }
+
+    impl Transport for NamedPipeTransport {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            Ok(Read::read(&mut self.read, buf)?)
+        }
+
+        fn write_all(&mut self, data: &[u8]) -> Result<()> {
+            self.write.write_all(data)?;
+            self.write.flush()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use unix_impl::UnixSocketTransport;
+
+#[cfg(unix)]
+mod unix_impl {
+    use super::{Result, Transport};
+    use anyhow::anyhow;
+    use std::mem::size_of;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::os::unix::net::UnixStream;
+
+    /// Unix domain socket 传输，额外支持通过 `SCM_RIGHTS` 辅助消息随数据一起
+    /// 传递一个文件描述符（用于把共享内存 fd 交给对端），对应 audioipc2
+    /// `sys/unix/cmsg` 的做法
+    pub struct UnixSocketTransport {
+        stream: UnixStream,
+    }
+
+    impl UnixSocketTransport {
+        pub fn connect(path: &str) -> Result<Self> {
+            let stream = UnixStream::connect(path)
+                .map_err(|e| anyhow!("connect unix socket '{}' failed: {}", path, e))?;
+            Ok(Self { stream })
+        }
+
+        /// glibc `CMSG_ALIGN`：按 `size_t` 对齐
+        fn cmsg_align(len: usize) -> usize {
+            let align = size_of::<usize>();
+            (len + align - 1) & !(align - 1)
+        }
+
+        /// glibc `CMSG_SPACE`：一条辅助消息（含 header）占用的总字节数
+        fn cmsg_space(len: usize) -> usize {
+            Self::cmsg_align(size_of::<libc::cmsghdr>()) + Self::cmsg_align(len)
+        }
+
+        /// glibc `CMSG_LEN`：写入 `cmsghdr.cmsg_len` 字段的值
+        fn cmsg_len(len: usize) -> usize {
+            Self::cmsg_align(size_of::<libc::cmsghdr>()) + len
+        }
+
+        /// 把 `data` 和一个 fd 一起通过 `sendmsg` 发送：`data` 走普通 iovec，
+        /// fd 走 `SCM_RIGHTS` 辅助数据
+        pub fn send_with_fd(&self, data: &[u8], fd: RawFd) -> Result<usize> {
+            unsafe {
+                let mut iov = libc::iovec {
+                    iov_base: data.as_ptr() as *mut libc::c_void,
+                    iov_len: data.len(),
+                };
+
+                let space = Self::cmsg_space(size_of::<RawFd>());
+                let mut cmsg_buf = vec![0u8; space];
+
+                let mut msg: libc::msghdr = std::mem::zeroed();
+                msg.msg_iov = &mut iov;
+                msg.msg_iovlen = 1;
+                msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+                msg.msg_controllen = space as _;
+
+                let cmsg = msg.msg_control as *mut libc::cmsghdr;
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = Self::cmsg_len(size_of::<RawFd>()) as _;
+                let data_ptr = cmsg.add(1) as *mut u8;
+                std::ptr::write_unaligned(data_ptr as *mut RawFd, fd);
+
+                let n = libc::sendmsg(self.stream.as_raw_fd(), &msg, 0);
+                if n < 0 {
+                    return Err(anyhow!("sendmsg failed: {}", std::io::Error::last_os_error()));
+                }
+                Ok(n as usize)
+            }
+        }
+
+        /// 用 `recvmsg` 接收数据，并在携带了 `SCM_RIGHTS` 辅助数据时一并取出 fd
+        pub fn recv_with_fd(&self, buf: &mut [u8]) -> Result<(usize, Option<RawFd>)> {
+            unsafe {
+                let mut iov = libc::iovec {
+                    iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: buf.len(),
+                };
+
+                let space = Self::cmsg_space(size_of::<RawFd>());
+                let mut cmsg_buf = vec![0u8; space];
+
+                let mut msg: libc::msghdr = std::mem::zeroed();
+                msg.msg_iov = &mut iov;
+                msg.msg_iovlen = 1;
+                msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+                msg.msg_controllen = space as _;
+
+                let n = libc::recvmsg(self.stream.as_raw_fd(), &mut msg, 0);
+                if n < 0 {
+                    return Err(anyhow!("recvmsg failed: {}", std::io::Error::last_os_error()));
+                }
+
+                let mut fd = None;
+                if msg.msg_controllen >= size_of::<libc::cmsghdr>() as _ {
+                    let cmsg = msg.msg_control as *const libc::cmsghdr;
+                    if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                        let data_ptr = cmsg.add(1) as *const u8;
+                        fd = Some(std::ptr::read_unaligned(data_ptr as *const RawFd));
+                    }
+                }
+
+                Ok((n as usize, fd))
+            }
+        }
+    }
+
+    impl Transport for UnixSocketTransport {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            use std::io::Read;
+            Ok(Read::read(&mut self.stream, buf)?)
+        }
+
+        fn write_all(&mut self, data: &[u8]) -> Result<()> {
+            use std::io::Write;
+            Write::write_all(&mut self.stream, data)?;
+            Ok(())
+        }
+    }
+}
@@ -1,26 +1,54 @@
-use crate::config::{Config, StorageConfig};
-use crate::file_manager::{FileManager, FileInfo, ProcessedDataFile};
-use crate::device_communication::{DeviceCommand, ChannelConfig};
-use crate::data_processing::{DataProcessor, TriggerSummary, TriggerBurst};
+use crate::config::{Config, ConfigController, ConfigUpdate, StorageConfig};
+use crate::file_manager::{FileManager, FileInfo, ProcessedDataFile, StorageStats};
+use crate::device_communication::{DeviceCommand, ChannelConfig, TriggerEvent};
+use crate::data_processing::{DataProcessor, ProcessedData, TriggerSummary, TriggerBurst, TriggerListFilter, TriggerListPage, BatchSizeBucket};
+use crate::ipc::SequenceGapStats;
+use crate::metrics::{PipelineMetrics, RuntimeMetrics};
+use crate::observability::{ErrorContext, Observability};
+use crate::websocket::WsMetrics;
 use anyhow::Result;
 use axum::{
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
+    body::Body,
+    extract::{
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
     response::{IntoResponse, Json, Response},
     routing::{get, post, delete},
     Router,
 };
 use data_encoding::BASE64;
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Instant;
-use tokio::sync::{watch, Mutex, mpsc};
+use tokio::sync::{broadcast, watch, Mutex, RwLock, mpsc};
 use tower::ServiceBuilder;
+use tower_http::catch_panic::CatchPanicLayer;
 use tower_http::cors::CorsLayer;
 use tracing::{info, warn, error};
+use utoipa::OpenApi as _;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// panic 捕获层拿不到 `AppState`（`CatchPanicLayer` 的处理函数没有状态注入），
+/// 用这个进程级句柄把 panic 事件转交给可观测性子系统；其余地方一律走显式的状态传递。
+static PANIC_OBSERVABILITY: OnceLock<Observability> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[aliases(
+    ApiResponseString = ApiResponse<String>,
+    ApiResponseStatus = ApiResponse<SystemStatus>,
+    ApiResponseSaveTrigger = ApiResponse<SaveTriggerResponse>,
+    ApiResponseFiles = ApiResponse<Vec<FileInfo>>,
+    ApiResponseConfig = ApiResponse<Config>,
+    ApiResponseStorageStats = ApiResponse<StorageStats>,
+    ApiResponseTriggerList = ApiResponse<TriggerListPage>,
+    ApiResponseStatusReport = ApiResponse<StatusReport>,
+)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
@@ -28,13 +56,13 @@ pub struct ApiResponse<T> {
     pub timestamp: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ControlCommand {
     pub command: String,
     pub parameters: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct SystemStatus {
     pub data_collection_active: bool,
     pub device_connected: bool,
@@ -48,7 +76,7 @@ pub struct SystemStatus {
     pub trigger_status: Option<TriggerStatus>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct TriggerStatus {
     pub cached_bursts: usize,
     pub current_burst_active: bool,
@@ -58,7 +86,7 @@ pub struct TriggerStatus {
 
 #[derive(Clone)]
 pub struct AppState {
-    cfg: Config,
+    config: ConfigController,
     device_command_tx: mpsc::UnboundedSender<DeviceCommand>,
     start_at: Instant,
     packets_rx: watch::Receiver<u64>,
@@ -66,12 +94,27 @@ pub struct AppState {
     collecting: Arc<Mutex<bool>>,
     device_status_rx: watch::Receiver<bool>,
     current_mode: Arc<Mutex<Option<String>>>,
-    file_manager: Arc<FileManager>,
+    file_manager: Arc<RwLock<Arc<FileManager>>>,
     data_processor: Arc<Mutex<DataProcessor>>,
+    processed_tx: broadcast::Sender<ProcessedData>,
+    trigger_event_tx: broadcast::Sender<TriggerEvent>,
+    trigger_burst_tx: broadcast::Sender<TriggerBurst>,
+    stream_clients: Arc<AtomicUsize>,
+    metrics: RuntimeMetrics,
+    /// 服务启动的墙钟时间（RFC3339），供 `/api/control/info` 的 server 段上报
+    server_start_time: String,
+    observability: Observability,
+    /// WebSocket 子系统的 Prometheus 指标句柄，供 `/metrics` 路由直接 text_encode
+    ws_metrics: WsMetrics,
+    /// 数据采集流水线（包/触发批次/IPC）的 Prometheus 指标句柄，同样供 `/metrics` 路由
+    pipeline_metrics: PipelineMetrics,
 }
 
 pub struct WebServer {
     state: AppState,
+    // 监听地址在启动时固定；运行时可重配置的是 AppState.config 里的设备/存储设置
+    listen_host: String,
+    listen_port: u16,
 }
 
 impl WebServer {
@@ -82,12 +125,22 @@ impl WebServer {
         clients_rx: watch::Receiver<usize>,
         data_processor: Arc<Mutex<DataProcessor>>,
         device_status_rx: watch::Receiver<bool>,
+        processed_tx: broadcast::Sender<ProcessedData>,
+        trigger_event_tx: broadcast::Sender<TriggerEvent>,
+        trigger_burst_tx: broadcast::Sender<TriggerBurst>,
+        metrics: RuntimeMetrics,
+        observability: Observability,
+        ws_metrics: WsMetrics,
+        pipeline_metrics: PipelineMetrics,
     ) -> Self {
-        let fm = FileManager::new(&config.storage.data_dir)
-            .expect("failed to init data directory");
+        let fm = FileManager::new(&config.storage)
+            .expect("failed to init storage backend");
+        let web_host = config.web_server.host.clone();
+        let web_port = config.web_server.port;
+        let _ = PANIC_OBSERVABILITY.get_or_init(|| observability.clone());
         Self {
             state: AppState {
-                cfg: config,
+                config: ConfigController::new(config),
                 device_command_tx,
                 start_at: Instant::now(),
                 packets_rx,
@@ -95,58 +148,180 @@ impl WebServer {
                 collecting: Arc::new(Mutex::new(false)),
                 device_status_rx,
                 current_mode: Arc::new(Mutex::new(None)),
-                file_manager: Arc::new(fm),
+                file_manager: Arc::new(RwLock::new(Arc::new(fm))),
                 data_processor,
+                processed_tx,
+                trigger_event_tx,
+                trigger_burst_tx,
+                stream_clients: Arc::new(AtomicUsize::new(0)),
+                metrics,
+                server_start_time: chrono::Utc::now().to_rfc3339(),
+                observability,
+                ws_metrics,
+                pipeline_metrics,
             },
+            listen_host: web_host,
+            listen_port: web_port,
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
+    /// `shutdown` 收到 `true` 时停止接受新连接，等待存量请求处理完（axum 的
+    /// graceful shutdown）后返回
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) -> Result<()> {
         let app = self.create_router();
 
-        let addr = format!(
-            "{}:{}",
-            self.state.cfg.web_server.host, self.state.cfg.web_server.port
-        );
+        let addr = format!("{}:{}", self.listen_host, self.listen_port);
         info!("Starting HTTP server on {}", addr);
 
         let listener = tokio::net::TcpListener::bind(&addr).await?;
-        axum::serve(listener, app).await?;
+        // 带上连接信息（客户端地址），供可观测性中间件在没有 X-Forwarded-For 时兜底取用
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
+            loop {
+                if shutdown.changed().await.is_err() || *shutdown.borrow() {
+                    break;
+                }
+            }
+        })
+        .await?;
         Ok(())
     }
 
-    fn create_router(&self) -> Router {
+    /// v2 路由：当前唯一实现，未来的不兼容改动应在此新增 v3 而不是破坏这里
+    fn v2_routes() -> Router<AppState> {
         Router::new()
             // 控制API
-            .route("/api/control/start", post(start_collection))
-            .route("/api/control/stop", post(stop_collection))
-            .route("/api/control/status", get(get_status))
-            .route("/api/control/ping", post(send_ping))
-            .route("/api/control/device_info", post(get_device_info))
-            .route("/api/control/configure", post(configure_stream))
-            .route("/api/control/continuous_mode", post(set_continuous_mode))
-            .route("/api/control/trigger_mode", post(set_trigger_mode))
-            .route("/api/control/request_trigger_data", post(request_trigger_data))
+            .route("/control/start", post(start_collection))
+            .route("/control/stop", post(stop_collection))
+            .route("/control/status", get(get_status))
+            .route("/control/info", get(get_status_info))
+            .route("/control/ping", post(send_ping))
+            .route("/control/device_info", post(get_device_info))
+            .route("/control/configure", post(configure_stream))
+            .route("/control/continuous_mode", post(set_continuous_mode))
+            .route("/control/trigger_mode", post(set_trigger_mode))
+            .route("/control/request_trigger_data", post(request_trigger_data))
             // 触发数据管理API
-            .route("/api/trigger/list", get(list_trigger_bursts))
-            .route("/api/trigger/preview/:burst_id", get(preview_trigger_burst))
-            .route("/api/trigger/save/:burst_id", post(save_trigger_burst))
-            .route("/api/trigger/delete/:burst_id", delete(delete_trigger_burst))
+            .route("/trigger/list", get(list_trigger_bursts))
+            .route("/trigger/preview/:burst_id", get(preview_trigger_burst))
+            .route("/trigger/save/:burst_id", post(save_trigger_burst))
+            .route("/trigger/delete/:burst_id", delete(delete_trigger_burst))
             // 文件管理API
-            .route("/api/files", get(list_files))
-            .route("/api/files/:filename", get(download_file))
-            .route("/api/files/save", post(save_waveform))
+            .route("/files", get(list_files))
+            .route("/files/stats", get(get_storage_stats))
+            .route("/files/:filename", get(download_file))
+            .route("/files/save", post(save_waveform))
+            // 运行时配置
+            .route("/config", get(get_config).put(update_config))
+    }
+
+    fn create_router(&self) -> Router {
+        Router::new()
+            .nest("/api/v2", Self::v2_routes())
+            // 未加版本号的旧路由：保留作为 v2 的弃用别名，新代码请改用 /api/v2/*
+            .nest("/api", Self::v2_routes())
+            // OpenAPI 3.0 文档
+            .route("/api/openapi.json", get(openapi_json))
+            // 实时数据流（WebSocket）
+            .route("/api/stream", get(stream_handler))
             // 健康检查
             .route("/health", get(health_check))
+            // Prometheus 抓取端点（WebSocket 子系统与数据采集流水线的指标）
+            .route("/metrics", get(metrics_handler))
             // 根路径重定向到API文档
             .route("/", get(api_info))
             .with_state(self.state.clone())
-            .layer(ServiceBuilder::new().layer(CorsLayer::permissive()))
+            .layer(middleware::from_fn_with_state(
+                self.state.clone(),
+                observability_middleware,
+            ))
+            .layer(
+                ServiceBuilder::new()
+                    .layer(CorsLayer::permissive())
+                    .layer(CatchPanicLayer::custom(observability_panic_handler)),
+            )
     }
 }
 
+/// 跨 handler 的可观测性中间件：handler 返回错误状态（>=400）时，连同请求上下文
+/// （接口路径、尽力抽取的 burst/file id、客户端 IP、负载大小）一起上报
+async fn observability_middleware(
+    State(st): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let endpoint = req.uri().path().to_string();
+    let burst_or_file_id = extract_resource_id(&endpoint);
+    let client_ip = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(',').next().unwrap_or(v).trim().to_string())
+        .unwrap_or_else(|| addr.ip().to_string());
+    let payload_bytes = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let response = next.run(req).await;
+
+    if st.observability.is_enabled()
+        && (response.status().is_client_error() || response.status().is_server_error())
+    {
+        st.observability.capture_error(
+            format!("handler returned {}", response.status()),
+            ErrorContext {
+                endpoint,
+                burst_or_file_id,
+                client_ip: Some(client_ip),
+                payload_bytes,
+            },
+        );
+    }
+    response
+}
+
+/// 从已知携带资源 id 的路径里尽力抽取 burst_id / 文件名，供错误上报做上下文标注
+fn extract_resource_id(path: &str) -> Option<String> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [.., "trigger", verb, id] if matches!(*verb, "preview" | "save" | "delete") => {
+            Some(id.to_string())
+        }
+        [.., "files", name] if *name != "stats" && *name != "save" => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// `CatchPanicLayer` 的处理函数拿不到 `AppState`，只能走 [`PANIC_OBSERVABILITY`] 这个进程级句柄上报
+fn observability_panic_handler(err: Box<dyn std::any::Any + Send + 'static>) -> Response {
+    let message = if let Some(s) = err.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = err.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+    error!("handler panicked: {}", message);
+    if let Some(obs) = PANIC_OBSERVABILITY.get() {
+        obs.capture_error(format!("panic: {}", message), ErrorContext::default());
+    }
+    (StatusCode::INTERNAL_SERVER_ERROR, "internal server error").into_response()
+}
+
+/// GET /api/openapi.json — 根据请求/响应结构体生成的 OpenAPI 3.0 文档
+async fn openapi_json() -> Json<serde_json::Value> {
+    Json(serde_json::to_value(crate::openapi::ApiDoc::openapi()).unwrap_or_default())
+}
+
 // ============ API 处理函数 ============
 
+#[utoipa::path(post, path = "/api/v2/control/start", responses((status = 200, body = ApiResponseString)))]
 async fn start_collection(State(st): State<AppState>) -> Result<Json<ApiResponse<String>>, StatusCode> {
     info!("API: Start collection requested");
     
@@ -154,7 +329,8 @@ async fn start_collection(State(st): State<AppState>) -> Result<Json<ApiResponse
         let mut c = st.collecting.lock().await;
         *c = true;
     }
-    
+    st.observability.breadcrumb("control/start: data collection started");
+
     // 发送启动流命令
     if let Err(err) = st.device_command_tx.send(DeviceCommand::StartStream) {
         error!("Failed to send start command: {}", err);
@@ -169,6 +345,7 @@ async fn start_collection(State(st): State<AppState>) -> Result<Json<ApiResponse
     }))
 }
 
+#[utoipa::path(post, path = "/api/v2/control/stop", responses((status = 200, body = ApiResponseString)))]
 async fn stop_collection(State(st): State<AppState>) -> Result<Json<ApiResponse<String>>, StatusCode> {
     info!("API: Stop collection requested");
     
@@ -176,7 +353,8 @@ async fn stop_collection(State(st): State<AppState>) -> Result<Json<ApiResponse<
         let mut c = st.collecting.lock().await;
         *c = false;
     }
-    
+    st.observability.breadcrumb("control/stop: data collection stopped");
+
     // 发送停止流命令
     if let Err(err) = st.device_command_tx.send(DeviceCommand::StopStream) {
         error!("Failed to send stop command: {}", err);
@@ -232,7 +410,8 @@ async fn set_continuous_mode(State(st): State<AppState>) -> Result<Json<ApiRespo
         let mut mode = st.current_mode.lock().await;
         *mode = Some("continuous".to_string());
     }
-    
+    st.observability.set_mode("continuous");
+
     // 发送连续模式命令
     if let Err(err) = st.device_command_tx.send(DeviceCommand::SetModeContinuous) {
         error!("Failed to send continuous mode command: {}", err);
@@ -254,7 +433,8 @@ async fn set_trigger_mode(State(st): State<AppState>) -> Result<Json<ApiResponse
         let mut mode = st.current_mode.lock().await;
         *mode = Some("trigger".to_string());
     }
-    
+    st.observability.set_mode("trigger");
+
     // 发送触发模式命令
     if let Err(err) = st.device_command_tx.send(DeviceCommand::SetModeTrigger) {
         error!("Failed to send trigger mode command: {}", err);
@@ -300,12 +480,12 @@ async fn request_trigger_data(State(st): State<AppState>) -> Result<Json<ApiResp
     }))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
 struct ConfigureRequest {
     channels: Vec<ChannelConfigRequest>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
 struct ChannelConfigRequest {
     channel_id: u8,
     sample_rate: u32,
@@ -317,7 +497,11 @@ async fn configure_stream(
     Json(req): Json<ConfigureRequest>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
     info!("API: Configure stream requested with {} channels", req.channels.len());
-    
+    st.observability.breadcrumb(format!(
+        "control/configure: {} channel(s)",
+        req.channels.len()
+    ));
+
     let channels: Vec<ChannelConfig> = req.channels.into_iter()
         .map(|c| ChannelConfig {
             channel_id: c.channel_id,
@@ -340,50 +524,186 @@ async fn configure_stream(
     }))
 }
 
-// ============ 触发数据管理 ============
+// ============ 运行时配置 ============
+
+/// GET /api/config — 返回当前生效配置
+#[utoipa::path(get, path = "/api/v2/config", responses((status = 200, body = ApiResponseConfig)))]
+async fn get_config(State(st): State<AppState>) -> Json<ApiResponse<Config>> {
+    Json(ApiResponse {
+        success: true,
+        data: Some(st.config.snapshot().await),
+        error: None,
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+/// PUT /api/config — 校验并应用一次部分配置更新
+///
+/// `storage.*` 变化时会原地重建 FileManager（可能切换本地目录或 S3 目标）；
+/// `device.*` 变化时会向设备管理器下发 `DeviceCommand::Reconfigure`，触发一次带新连接参数的重连。
+#[utoipa::path(put, path = "/api/v2/config",
+    request_body = ConfigUpdate,
+    responses((status = 200, body = ApiResponseConfig), (status = 400, description = "Invalid config update")))]
+async fn update_config(
+    State(st): State<AppState>,
+    Json(update): Json<ConfigUpdate>,
+) -> Result<Json<ApiResponse<Config>>, StatusCode> {
+    let storage_changed = update.storage.is_some();
+    let device_changed = update.device.is_some();
+
+    let new_cfg = match st.config.apply_update(update).await {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            warn!("update_config rejected: {}", e);
+            return Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            }));
+        }
+    };
+
+    if storage_changed {
+        match FileManager::new(&new_cfg.storage) {
+            Ok(fm) => {
+                *st.file_manager.write().await = Arc::new(fm);
+                info!("Switched storage backend to {}", new_cfg.storage.backend);
+            }
+            Err(e) => {
+                error!("Failed to switch storage backend: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    if device_changed {
+        let device_config = crate::device_communication::DeviceConfig {
+            connection_type: match new_cfg.device.connection_type.as_str() {
+                "serial" => crate::device_communication::ConnectionType::Serial,
+                _ => crate::device_communication::ConnectionType::Socket,
+            },
+            serial_port: new_cfg.device.serial_port.clone(),
+            socket_address: new_cfg.device.socket_address.clone(),
+            baud_rate: new_cfg.device.baud_rate,
+            protocol: new_cfg.device.protocol.clone(),
+        };
+        if let Err(err) = st.device_command_tx.send(DeviceCommand::Reconfigure(device_config)) {
+            error!("Failed to send reconfigure command: {}", err);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
 
-/// 获取触发批次列表
-async fn list_trigger_bursts(
-    State(st): State<AppState>
-) -> Result<Json<ApiResponse<Vec<TriggerSummary>>>, StatusCode> {
-    let processor = st.data_processor.lock().await;
-    let summaries = processor.get_trigger_summaries();
-    
-    info!("Listed {} trigger bursts", summaries.len());
-    
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(summaries),
+        data: Some(new_cfg),
         error: None,
         timestamp: chrono::Utc::now().timestamp_millis(),
     }))
 }
 
-/// 预览触发批次详细信息
-async fn preview_trigger_burst(
+// ============ 触发数据管理 ============
+
+/// `/api/v2/trigger/list` 的分页与筛选参数。`limit`/`offset` 未提供时分别默认为 50 / 0
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+struct TriggerListQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    /// 起始时间（created_at 的毫秒时间戳，含）
+    from_ts: Option<i64>,
+    /// 结束时间（created_at 的毫秒时间戳，含）
+    to_ts: Option<i64>,
+    trigger_channel: Option<u16>,
+    /// "Good" / "Warning" / "Error"
+    quality: Option<String>,
+}
+
+/// 获取触发批次列表（按时间倒序分页，支持时间范围/通道/质量筛选）
+#[utoipa::path(get, path = "/api/v2/trigger/list",
+    params(TriggerListQuery),
+    responses((status = 200, body = ApiResponseTriggerList)))]
+async fn list_trigger_bursts(
     State(st): State<AppState>,
-    Path(burst_id): Path<String>
-) -> Result<Json<ApiResponse<TriggerBurst>>, StatusCode> {
+    Query(q): Query<TriggerListQuery>,
+) -> Result<Json<ApiResponse<TriggerListPage>>, StatusCode> {
     let processor = st.data_processor.lock().await;
-    
-    match processor.get_trigger_burst(&burst_id) {
-        Some(burst) => {
-            info!("Previewed trigger burst: {}", burst_id);
+    let filter = TriggerListFilter {
+        limit: q.limit.unwrap_or(50),
+        offset: q.offset.unwrap_or(0),
+        from_ts: q.from_ts,
+        to_ts: q.to_ts,
+        trigger_channel: q.trigger_channel,
+        quality: q.quality,
+    };
+
+    match processor.list_trigger_summaries(&filter) {
+        Ok(page) => {
+            info!("Listed {} of {} trigger bursts", page.items.len(), page.total);
             Ok(Json(ApiResponse {
                 success: true,
-                data: Some(burst.clone()),
+                data: Some(page),
                 error: None,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             }))
         }
+        Err(e) => {
+            error!("Failed to list trigger bursts: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+struct TriggerPreviewQuery {
+    /// json（默认，包在 ApiResponse 里）/ cbor / bincode：后两者直接返回对应编码的原始字节
+    format: Option<String>,
+}
+
+/// 预览触发批次详细信息。`?format=cbor`/`?format=bincode` 时直接返回该编码的原始字节，
+/// 并设置匹配的 `Content-Type`，省去下游再做一次 JSON 解析
+#[utoipa::path(get, path = "/api/v2/trigger/preview/{burst_id}",
+    params(("burst_id" = String, Path, description = "触发批次 ID"), TriggerPreviewQuery),
+    responses((status = 200, body = TriggerBurst), (status = 404, description = "Burst not found")))]
+async fn preview_trigger_burst(
+    State(st): State<AppState>,
+    Path(burst_id): Path<String>,
+    Query(q): Query<TriggerPreviewQuery>,
+) -> Result<Response, StatusCode> {
+    let format = q.format.as_deref().unwrap_or("json");
+    let mut processor = st.data_processor.lock().await;
+
+    let burst = match processor.get_trigger_burst(&burst_id) {
+        Some(b) => b,
         None => {
             warn!("Trigger burst not found: {}", burst_id);
-            Err(StatusCode::NOT_FOUND)
+            return Err(StatusCode::NOT_FOUND);
+        }
+    };
+
+    if format == "json" {
+        info!("Previewed trigger burst: {}", burst_id);
+        return Ok(Json(ApiResponse {
+            success: true,
+            data: Some(burst),
+            error: None,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        }).into_response());
+    }
+
+    match processor.export_trigger_burst(&burst_id, format) {
+        Ok(bytes) => {
+            info!("Previewed trigger burst: {} as {}", burst_id, format);
+            let headers = [(header::CONTENT_TYPE, format_content_type(format))];
+            Ok((headers, bytes).into_response())
+        }
+        Err(e) => {
+            warn!("preview_trigger_burst: unsupported format {}: {}", format, e);
+            Err(StatusCode::BAD_REQUEST)
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
 struct SaveTriggerRequest {
     /// 保存的子目录路径（相对于data_dir）
     pub dir: Option<String>,
@@ -395,7 +715,7 @@ struct SaveTriggerRequest {
     pub description: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
 struct SaveTriggerResponse {
     pub saved_path: String,
     pub format: String,
@@ -404,13 +724,17 @@ struct SaveTriggerResponse {
 }
 
 /// 保存触发批次数据
+#[utoipa::path(post, path = "/api/v2/trigger/save/{burst_id}",
+    params(("burst_id" = String, Path, description = "触发批次 ID")),
+    request_body = SaveTriggerRequest,
+    responses((status = 200, body = ApiResponseSaveTrigger)))]
 async fn save_trigger_burst(
     State(st): State<AppState>,
     Path(burst_id): Path<String>,
     Json(req): Json<SaveTriggerRequest>
 ) -> Result<Json<ApiResponse<SaveTriggerResponse>>, StatusCode> {
     // 验证格式
-    let valid_formats = ["json", "csv", "binary"];
+    let valid_formats = ["json", "csv", "binary", "cbor", "bincode"];
     if !valid_formats.contains(&req.format.as_str()) {
         return Ok(Json(ApiResponse {
             success: false,
@@ -422,8 +746,8 @@ async fn save_trigger_burst(
 
     // 获取数据
     let (burst_data, burst_summary) = {
-        let processor = st.data_processor.lock().await;
-        
+        let mut processor = st.data_processor.lock().await;
+
         let burst = match processor.get_trigger_burst(&burst_id) {
             Some(b) => b,
             None => {
@@ -458,7 +782,7 @@ async fn save_trigger_burst(
             trigger_timestamp: burst.trigger_timestamp,
             trigger_channel: burst.trigger_channel,
             total_samples: burst.total_samples,
-            duration_ms: processor.calculate_duration_ms(burst),
+            duration_ms: processor.calculate_duration_ms(&burst),
             created_at: burst.created_at,
             quality: match burst.quality_summary.overall_quality {
                 crate::data_processing::DataQuality::Good => "Good".to_string(),
@@ -472,12 +796,7 @@ async fn save_trigger_burst(
     };
 
     // 生成文件名
-    let extension = match req.format.as_str() {
-        "json" => ".json",
-        "csv" => ".csv",
-        "binary" => ".bin",
-        _ => ".dat",
-    };
+    let extension = format_extension(&req.format);
 
     let filename = req.filename
         .as_deref()
@@ -518,10 +837,18 @@ async fn save_trigger_burst(
     }
 
     // 保存文件
-    match st.file_manager.save_at(req.dir.as_deref(), &file_data) {
+    let fm = st.file_manager.read().await.clone();
+    let max_files = st.config.snapshot().await.storage.max_files;
+    st.metrics.begin_write();
+    let save_result = fm.save_at(req.dir.as_deref(), &file_data).await;
+    match &save_result {
+        Ok(saved_rel_path) => st.metrics.end_write(Some((saved_rel_path, file_data.bytes.len()))),
+        Err(_) => st.metrics.end_write(None),
+    }
+    match save_result {
         Ok(saved_rel_path) => {
             // 限制文件数量
-            let _ = st.file_manager.cleanup_old_files(st.cfg.storage.max_files);
+            let _ = fm.cleanup_old_files(max_files).await;
 
             info!("Saved trigger burst {} to {}", burst_id, saved_rel_path);
 
@@ -547,6 +874,9 @@ async fn save_trigger_burst(
 }
 
 /// 删除缓存的触发批次
+#[utoipa::path(delete, path = "/api/v2/trigger/delete/{burst_id}",
+    params(("burst_id" = String, Path, description = "触发批次 ID")),
+    responses((status = 200, body = ApiResponseString), (status = 404, description = "Burst not found")))]
 async fn delete_trigger_burst(
     State(st): State<AppState>,
     Path(burst_id): Path<String>
@@ -568,10 +898,23 @@ async fn delete_trigger_burst(
     }
 }
 
+#[utoipa::path(get, path = "/api/v2/control/status", responses((status = 200, body = ApiResponseStatus)))]
 async fn get_status(State(st): State<AppState>) -> Result<Json<ApiResponse<SystemStatus>>, StatusCode> {
-    // 汇总当前状态
+    let status = build_system_status(&st).await;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(status),
+        error: None,
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    }))
+}
+
+/// 汇总当前系统状态，供 HTTP 状态接口和 WebSocket 周期状态帧共用
+async fn build_system_status(st: &AppState) -> SystemStatus {
     let packets = *st.packets_rx.borrow();
-    let clients = *st.clients_rx.borrow();
+    let ws_clients = *st.clients_rx.borrow();
+    let stream_clients = st.stream_clients.load(Ordering::Relaxed);
     let collecting = *st.collecting.lock().await;
     let device_connected = *st.device_status_rx.borrow();
     let current_mode = st.current_mode.lock().await.clone();
@@ -588,40 +931,307 @@ async fn get_status(State(st): State<AppState>) -> Result<Json<ApiResponse<Syste
         })
     };
 
-    let status = SystemStatus {
+    let connection_type = st.config.snapshot().await.device.connection_type;
+
+    SystemStatus {
         data_collection_active: collecting,
         device_connected,
-        connected_clients: clients,
+        connected_clients: ws_clients + stream_clients,
         packets_processed: packets,
         uptime_seconds: st.start_at.elapsed().as_secs(),
         memory_usage_mb: get_memory_usage_mb(),
-        connection_type: st.cfg.device.connection_type.clone(),
+        connection_type,
         current_mode,
         trigger_support: true,
         trigger_status,
-    };
+    }
+}
+
+// ============ 聚合状态报告（/api/control/info，类似 Redis INFO） ============
+
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct ServerSection {
+    pub version: String,
+    pub pid: u32,
+    pub start_time: String,
+    pub uptime_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct MemorySection {
+    pub rss_mb: f64,
+    pub peak_rss_mb: f64,
+    /// 当前正在累积的触发批次占用的内存
+    pub current_burst_buffer_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct StatsSection {
+    pub total_bursts_captured: usize,
+    pub bytes_written: u64,
+    pub files_saved: u64,
+    pub websocket_messages_sent: u64,
+    pub clients_connected: usize,
+    /// 设备数据包的丢包/重传/重排统计
+    pub sequence_gaps: SequenceGapStats,
+    /// `process_packets` 批次大小分布，供调用方判断读取粒度是否合适
+    pub batch_size_histogram: Vec<BatchSizeBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct PersistenceSection {
+    pub last_save_path: Option<String>,
+    pub last_save_timestamp: Option<i64>,
+    pub pending_writes: u64,
+}
+
+/// 每个 section 独立可序列化，未被 `?sections=` 选中的字段直接省略
+#[derive(Debug, Clone, Default, Serialize, utoipa::ToSchema)]
+pub struct StatusReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemorySection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<StatsSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistence: Option<PersistenceSection>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+struct StatusInfoQuery {
+    /// 逗号分隔的 section 名：server,memory,stats,persistence；不填则返回全部
+    sections: Option<String>,
+}
+
+/// `GET /api/v2/control/info` — 类似 Redis `INFO` 的单一抓取点，覆盖服务器/内存/统计/持久化状态
+#[utoipa::path(get, path = "/api/v2/control/info",
+    params(StatusInfoQuery),
+    responses((status = 200, body = ApiResponseStatusReport)))]
+async fn get_status_info(
+    State(st): State<AppState>,
+    Query(q): Query<StatusInfoQuery>,
+) -> Result<Json<ApiResponse<StatusReport>>, StatusCode> {
+    let wanted: Option<Vec<String>> = q.sections.as_deref().map(|s| {
+        s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+    });
+    let want = |name: &str| wanted.as_ref().map_or(true, |w| w.iter().any(|s| s == name));
+
+    let rss_mb = get_memory_usage_mb();
+    st.metrics.note_rss_sample(rss_mb);
+    let snapshot = st.metrics.snapshot();
+
+    let mut report = StatusReport::default();
+
+    if want("server") {
+        report.server = Some(ServerSection {
+            version: "2.0".to_string(),
+            pid: std::process::id(),
+            start_time: st.server_start_time.clone(),
+            uptime_seconds: st.start_at.elapsed().as_secs(),
+        });
+    }
+
+    if want("memory") {
+        let buffer_bytes = st.data_processor.lock().await.current_burst_buffer_bytes();
+        report.memory = Some(MemorySection {
+            rss_mb,
+            peak_rss_mb: snapshot.peak_rss_mb,
+            current_burst_buffer_bytes: buffer_bytes,
+        });
+    }
+
+    if want("stats") {
+        let processor_stats = st.data_processor.lock().await.get_stats();
+        let clients_connected = *st.clients_rx.borrow() + st.stream_clients.load(Ordering::Relaxed);
+        report.stats = Some(StatsSection {
+            total_bursts_captured: processor_stats.cached_bursts_count,
+            bytes_written: snapshot.bytes_written,
+            files_saved: snapshot.files_saved,
+            websocket_messages_sent: snapshot.ws_messages_sent,
+            clients_connected,
+            sequence_gaps: processor_stats.sequence_gap_stats,
+            batch_size_histogram: processor_stats.batch_size_histogram,
+        });
+    }
+
+    if want("persistence") {
+        report.persistence = Some(PersistenceSection {
+            last_save_path: snapshot.last_save.as_ref().map(|l| l.path.clone()),
+            last_save_timestamp: snapshot.last_save.as_ref().map(|l| l.timestamp),
+            pending_writes: snapshot.pending_writes,
+        });
+    }
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(status),
+        data: Some(report),
         error: None,
         timestamp: chrono::Utc::now().timestamp_millis(),
     }))
 }
 
+// ============ 实时数据流（/api/stream） ============
+
+/// 客户端通过 `{"type":"subscribe", ...}` 消息选择想要的数据
+#[derive(Debug, Default, Deserialize)]
+struct StreamSubscribeRequest {
+    /// true: 接收连续采集的数据样本
+    #[serde(default)]
+    data: bool,
+    /// true: 接收触发事件与触发批次完成事件
+    #[serde(default)]
+    trigger_events: bool,
+    /// 仅接收该通道的数据/触发事件（不设置则不过滤）
+    channel_id: Option<u16>,
+}
+
+impl StreamSubscribeRequest {
+    fn all() -> Self {
+        Self { data: true, trigger_events: true, channel_id: None }
+    }
+}
+
+async fn stream_handler(ws: WebSocketUpgrade, State(st): State<AppState>) -> Response {
+    ws.on_upgrade(move |socket| handle_stream_socket(socket, st))
+}
+
+async fn handle_stream_socket(socket: WebSocket, st: AppState) {
+    let (mut sender, mut receiver) = socket.split();
+
+    st.stream_clients.fetch_add(1, Ordering::Relaxed);
+    info!("Live stream client connected, total={}", st.stream_clients.load(Ordering::Relaxed));
+
+    let welcome = json!({
+        "type": "welcome",
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+    });
+    if let Ok(text) = serde_json::to_string(&welcome) {
+        let _ = sender.send(WsMessage::Text(text)).await;
+    }
+
+    let mut subscription = StreamSubscribeRequest::all();
+    let mut data_rx = st.processed_tx.subscribe();
+    let mut trigger_rx = st.trigger_event_tx.subscribe();
+    let mut burst_rx = st.trigger_burst_tx.subscribe();
+    let mut status_tick = tokio::time::interval(std::time::Duration::from_secs(5));
+
+    loop {
+        tokio::select! {
+            data = data_rx.recv() => {
+                let Ok(data) = data else { continue };
+                if !subscription.data { continue; }
+                if let Some(ch) = subscription.channel_id {
+                    if !data.metadata.channel_info.iter().any(|c| c.channel_id as u16 == ch) { continue; }
+                }
+                let payload = json!({
+                    "type": "data",
+                    "timestamp": data.timestamp,
+                    "sequence": data.sequence,
+                    "channel_count": data.channel_count,
+                    "sample_rate": data.sample_rate,
+                    "data": data.data,
+                    "metadata": data.metadata,
+                    "data_type": data.data_type,
+                });
+                if let Ok(text) = serde_json::to_string(&payload) {
+                    if sender.send(WsMessage::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            evt = trigger_rx.recv() => {
+                let Ok(evt) = evt else { continue };
+                if !subscription.trigger_events { continue; }
+                if let Some(ch) = subscription.channel_id {
+                    if evt.channel != ch { continue; }
+                }
+                let payload = json!({
+                    "type": "trigger_event",
+                    "timestamp": evt.timestamp,
+                    "channel": evt.channel,
+                    "pre_samples": evt.pre_samples,
+                    "post_samples": evt.post_samples,
+                });
+                if let Ok(text) = serde_json::to_string(&payload) {
+                    if sender.send(WsMessage::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            burst = burst_rx.recv() => {
+                let Ok(burst) = burst else { continue };
+                if !subscription.trigger_events { continue; }
+                if let Some(ch) = subscription.channel_id {
+                    if burst.trigger_channel != ch { continue; }
+                }
+                let payload = json!({
+                    "type": "trigger_burst_complete",
+                    "burst_id": burst.burst_id,
+                    "trigger_channel": burst.trigger_channel,
+                    "total_samples": burst.total_samples,
+                    "is_complete": burst.is_complete,
+                });
+                if let Ok(text) = serde_json::to_string(&payload) {
+                    if sender.send(WsMessage::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            _ = status_tick.tick() => {
+                let status = build_system_status(&st).await;
+                let payload = json!({ "type": "status", "status": status });
+                if let Ok(text) = serde_json::to_string(&payload) {
+                    if sender.send(WsMessage::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
+                            if v.get("type").and_then(|t| t.as_str()) == Some("subscribe") {
+                                subscription = StreamSubscribeRequest {
+                                    data: v.get("data").and_then(|b| b.as_bool()).unwrap_or(false),
+                                    trigger_events: v.get("trigger_events").and_then(|b| b.as_bool()).unwrap_or(false),
+                                    channel_id: v.get("channel_id").and_then(|c| c.as_u64()).map(|c| c as u16),
+                                };
+                            }
+                        }
+                    }
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        warn!("stream socket error: {}", e);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    st.stream_clients.fetch_sub(1, Ordering::Relaxed);
+    info!("Live stream client disconnected, total={}", st.stream_clients.load(Ordering::Relaxed));
+}
+
 // ============ 文件管理 ============
 
 /// GET /api/files?dir=相对目录
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
 struct ListQuery {
     dir: Option<String>,
 }
 
+#[utoipa::path(get, path = "/api/v2/files",
+    params(ListQuery),
+    responses((status = 200, body = ApiResponseFiles)))]
 async fn list_files(
     State(st): State<AppState>,
     Query(q): Query<ListQuery>,
 ) -> Result<Json<ApiResponse<Vec<FileInfo>>>, StatusCode> {
-    match st.file_manager.list_files_in(q.dir.as_deref()) {
+    let fm = st.file_manager.read().await.clone();
+    match fm.list_files_in(q.dir.as_deref()).await {
         Ok(files) => {
             info!("Listed {} files in dir: {:?}", files.len(), q.dir);
             Ok(Json(ApiResponse {
@@ -638,32 +1248,127 @@ async fn list_files(
     }
 }
 
+/// GET /api/files/stats — 分块去重存储的逻辑/物理字节数与去重比例
+#[utoipa::path(get, path = "/api/v2/files/stats", responses((status = 200, body = ApiResponseStorageStats)))]
+async fn get_storage_stats(State(st): State<AppState>) -> Result<Json<ApiResponse<StorageStats>>, StatusCode> {
+    let fm = st.file_manager.read().await.clone();
+    match fm.storage_stats().await {
+        Ok(stats) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(stats),
+            error: None,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })),
+        Err(e) => {
+            warn!("get_storage_stats failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 /// GET /api/files/:filename   （支持子目录：例如 runs/2025-08-26/wave.bin）
+/// 以固定大小缓冲流式返回，内存占用与文件大小无关；支持 `Range` 请求以断点续传
+#[utoipa::path(get, path = "/api/v2/files/{filename}",
+    params(("filename" = String, Path, description = "相对 data_dir 的文件路径")),
+    responses(
+        (status = 200, description = "文件内容（二进制流）"),
+        (status = 206, description = "部分内容（命中 Range 请求）"),
+        (status = 404, description = "File not found"),
+        (status = 416, description = "请求的字节区间不满足"),
+    ))]
 async fn download_file(
     State(st): State<AppState>,
     Path(filename): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, StatusCode> {
-    match st.file_manager.read_file(&filename) {
-        Ok(bytes) => {
-            let cd = format!(
-                "attachment; filename=\"{}\"", 
-                filename.split(|c| c == '/' || c == '\\').last().unwrap_or(&filename)
-            );
-            let headers = [
-                (header::CONTENT_TYPE, "application/octet-stream"),
-                (header::CONTENT_DISPOSITION, cd.as_str()),
-            ];
-            info!("Downloaded file: {} ({} bytes)", filename, bytes.len());
-            Ok((headers, bytes).into_response())
-        }
+    let fm = st.file_manager.read().await.clone();
+    let total_len = match fm.file_len(&filename).await {
+        Ok(len) => len,
         Err(e) => {
             warn!("download_file failed: {} ({})", filename, e);
-            Err(StatusCode::NOT_FOUND)
+            return Err(StatusCode::NOT_FOUND);
+        }
+    };
+
+    let range = match headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(raw) => match parse_range_header(raw, total_len) {
+            Some(r) => Some(r),
+            None => {
+                let resp_headers = [(header::CONTENT_RANGE, format!("bytes */{}", total_len))];
+                return Ok((StatusCode::RANGE_NOT_SATISFIABLE, resp_headers).into_response());
+            }
+        },
+        None => None,
+    };
+
+    let stream = match fm.read_file_stream(&filename, range).await {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("download_file stream open failed: {} ({})", filename, e);
+            return Err(StatusCode::NOT_FOUND);
+        }
+    };
+
+    let short_name = filename.split(|c| c == '/' || c == '\\').last().unwrap_or(&filename);
+    let cd = format!("attachment; filename=\"{}\"", short_name);
+    let body = Body::from_stream(stream);
+
+    Ok(match range {
+        Some((start, end)) => {
+            info!("Downloaded file (range): {} bytes={}-{}/{}", filename, start, end, total_len);
+            let resp_headers = [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (header::CONTENT_DISPOSITION, cd),
+                (header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total_len)),
+                (header::CONTENT_LENGTH, (end - start + 1).to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+            ];
+            (StatusCode::PARTIAL_CONTENT, resp_headers, body).into_response()
+        }
+        None => {
+            info!("Downloaded file: {} ({} bytes)", filename, total_len);
+            let resp_headers = [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (header::CONTENT_DISPOSITION, cd),
+                (header::CONTENT_LENGTH, total_len.to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+            ];
+            (resp_headers, body).into_response()
         }
+    })
+}
+
+/// 解析单一区间的 `Range: bytes=start-end` / `bytes=start-` / `bytes=-suffix`；
+/// 越界、空文件或无法解析时返回 `None`（调用方应回应 416）。多区间请求只取第一段。
+fn parse_range_header(raw: &str, total_len: u64) -> Option<(u64, u64)> {
+    let spec = raw.strip_prefix("bytes=")?;
+    let spec = spec.split(',').next()?.trim();
+    let (start_s, end_s) = spec.split_once('-')?;
+
+    let (start, end) = if start_s.is_empty() {
+        let suffix: u64 = end_s.parse().ok()?;
+        if suffix == 0 || total_len == 0 {
+            return None;
+        }
+        let suffix = suffix.min(total_len);
+        (total_len - suffix, total_len - 1)
+    } else {
+        let start: u64 = start_s.parse().ok()?;
+        let end = if end_s.is_empty() {
+            total_len.saturating_sub(1)
+        } else {
+            end_s.parse().ok()?
+        };
+        (start, end)
+    };
+
+    if total_len == 0 || start >= total_len || start > end {
+        return None;
     }
+    Some((start, end.min(total_len - 1)))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
 struct SaveRequest {
     /// 相对 base 的子目录，例如 "runs/2025-08-26"（可选）
     dir: Option<String>,
@@ -674,6 +1379,9 @@ struct SaveRequest {
 }
 
 /// POST /api/files/save
+#[utoipa::path(post, path = "/api/v2/files/save",
+    request_body = SaveRequest,
+    responses((status = 200, body = ApiResponseString)))]
 async fn save_waveform(
     State(st): State<AppState>,
     Json(req): Json<SaveRequest>,
@@ -687,6 +1395,8 @@ async fn save_waveform(
         }
     };
 
+    let storage_cfg = st.config.snapshot().await.storage;
+
     // 文件名：若未提供或为空，则根据配置自动生成
     let filename = req
         .filename
@@ -694,17 +1404,24 @@ async fn save_waveform(
             let s = s.trim().to_string();
             if s.is_empty() { None } else { Some(s) }
         })
-        .unwrap_or_else(|| make_auto_filename(&st.cfg.storage));
+        .unwrap_or_else(|| make_auto_filename(&storage_cfg));
 
     let data = ProcessedDataFile {
         filename: filename.clone(),
         bytes,
     };
 
-    match st.file_manager.save_at(req.dir.as_deref(), &data) {
+    let fm = st.file_manager.read().await.clone();
+    st.metrics.begin_write();
+    let save_result = fm.save_at(req.dir.as_deref(), &data).await;
+    match &save_result {
+        Ok(saved_rel_path) => st.metrics.end_write(Some((saved_rel_path, data.bytes.len()))),
+        Err(_) => st.metrics.end_write(None),
+    }
+    match save_result {
         Ok(saved_rel_path) => {
             // 限制 base 根目录下的总文件数（不递归）
-            let _ = st.file_manager.cleanup_old_files(st.cfg.storage.max_files);
+            let _ = fm.cleanup_old_files(storage_cfg.max_files).await;
 
             info!("Saved file: {} ({} bytes)", saved_rel_path, data.bytes.len());
             Ok(Json(ApiResponse {
@@ -721,7 +1438,9 @@ async fn save_waveform(
     }
 }
 
+#[utoipa::path(get, path = "/health", responses((status = 200, description = "Service health summary")))]
 async fn health_check() -> Json<ApiResponse<serde_json::Value>> {
+    let resources = crate::resource_metrics::sample();
     Json(ApiResponse {
         success: true,
         data: Some(json!({
@@ -729,6 +1448,7 @@ async fn health_check() -> Json<ApiResponse<serde_json::Value>> {
             "service": "data-processor",
             "version": "2.0",
             "trigger_support": true,
+            "resources": resources,
             "timestamp": chrono::Utc::now().to_rfc3339()
         })),
         error: None,
@@ -736,11 +1456,26 @@ async fn health_check() -> Json<ApiResponse<serde_json::Value>> {
     })
 }
 
+/// Prometheus 文本格式的指标抓取端点，不属于 OpenAPI 描述的 JSON API 表面。
+/// WebSocket 子系统和数据处理流水线各自持有独立的 `Registry`，这里简单拼接
+/// 两份 text-exposition 输出一起返回
+async fn metrics_handler(State(st): State<AppState>) -> impl IntoResponse {
+    let mut body = st.ws_metrics.encode();
+    body.push_str(&st.pipeline_metrics.encode());
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+}
+
 async fn api_info() -> Json<serde_json::Value> {
     Json(json!({
         "name": "Integrated Data Processor API",
         "version": "2.0",
         "description": "High-performance data acquisition and processing system with enhanced trigger support",
+        "openapi": "/api/openapi.json",
+        "api_base": "/api/v2",
+        "deprecated_unversioned_routes": "/api/* (mirrors /api/v2/*, kept for backwards compatibility)",
         "features": {
             "continuous_mode": true,
             "trigger_mode": true,
@@ -786,16 +1521,29 @@ fn make_auto_filename(sto: &StorageConfig) -> String {
     format!("{}_{}{}", sto.default_prefix, ts, sto.default_ext)
 }
 
-fn get_memory_usage_mb() -> f64 {
-    // 简单的内存使用统计，可以用sysinfo库获取更精确的数据
-    #[cfg(target_os = "windows")]
-    {
-        // Windows平台可以通过GetProcessMemoryInfo获取
-        0.0
+/// 触发批次导出格式（json/csv/binary/cbor/bincode）对应的文件扩展名
+fn format_extension(format: &str) -> &'static str {
+    match format {
+        "json" => ".json",
+        "csv" => ".csv",
+        "binary" => ".bin",
+        "cbor" => ".cbor",
+        "bincode" => ".bincode",
+        _ => ".dat",
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        // Unix平台可以读取/proc/self/status
-        0.0
+}
+
+/// 触发批次导出格式对应的 HTTP `Content-Type`
+fn format_content_type(format: &str) -> &'static str {
+    match format {
+        "json" => "application/json",
+        "csv" => "text/csv",
+        "cbor" => "application/cbor",
+        "binary" | "bincode" => "application/octet-stream",
+        _ => "application/octet-stream",
     }
+}
+
+fn get_memory_usage_mb() -> f64 {
+    crate::resource_metrics::rss_mb()
 }
\ No newline at end of file
@@ -1,13 +1,20 @@
 use crate::data_processing::{ProcessedData, DataQuality, TriggerBurst};
 use crate::device_communication::TriggerEvent;
 use crate::config::WebSocketConfig;
+use crate::metrics::RuntimeMetrics;
 use anyhow::Result;
 use futures_util::{SinkExt, StreamExt};
+use http::Response;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock, watch};
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::handshake::server::{Request as HandshakeRequest, Response as HandshakeResponse},
+    tungstenite::Message,
+};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
@@ -19,11 +26,110 @@ pub struct WebSocketServer {
     trigger_burst_complete_receiver: broadcast::Receiver<TriggerBurst>,
     pub client_count_rx: watch::Receiver<usize>,
     client_count_tx: watch::Sender<usize>,
+    metrics: RuntimeMetrics,
+    pub ws_metrics: WsMetrics,
+}
+
+/// WebSocket 子系统的 Prometheus 指标句柄。克隆后仍指向同一份底层 `Registry`，
+/// 与仓库里其它共享状态句柄（`ConfigController`、`RuntimeMetrics`）的用法一致；
+/// 内部的 `prometheus` 指标类型本身也是 `Arc` 包装的廉价克隆句柄。
+#[derive(Clone)]
+pub struct WsMetrics {
+    registry: prometheus::Registry,
+    connected_clients: prometheus::IntGauge,
+    data_frames_total: prometheus::IntCounter,
+    trigger_events_total: prometheus::IntCounter,
+    trigger_bursts_total: prometheus::IntCounter,
+    frames_dropped_total: prometheus::IntCounter,
+    subscription_updates_total: prometheus::IntCounter,
+    broadcast_duration_seconds: prometheus::Histogram,
+}
+
+impl WsMetrics {
+    pub fn new() -> Self {
+        let registry = prometheus::Registry::new();
+
+        let connected_clients = prometheus::IntGauge::new(
+            "ws_connected_clients", "当前已连接的 WebSocket 客户端数",
+        ).unwrap();
+        let data_frames_total = prometheus::IntCounter::new(
+            "ws_data_frames_total", "已广播的数据帧总数",
+        ).unwrap();
+        let trigger_events_total = prometheus::IntCounter::new(
+            "ws_trigger_events_total", "已广播的触发事件总数",
+        ).unwrap();
+        let trigger_bursts_total = prometheus::IntCounter::new(
+            "ws_trigger_bursts_total", "已广播的触发批次完成事件总数",
+        ).unwrap();
+        let frames_dropped_total = prometheus::IntCounter::new(
+            "ws_frames_dropped_total", "因慢消费者发送队列已满而被丢弃的帧数",
+        ).unwrap();
+        let subscription_updates_total = prometheus::IntCounter::new(
+            "ws_subscription_updates_total", "客户端发起的订阅更新次数",
+        ).unwrap();
+        let broadcast_duration_seconds = prometheus::Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "ws_broadcast_duration_seconds", "单次广播的负载序列化+扇出耗时",
+            ),
+        ).unwrap();
+
+        registry.register(Box::new(connected_clients.clone())).unwrap();
+        registry.register(Box::new(data_frames_total.clone())).unwrap();
+        registry.register(Box::new(trigger_events_total.clone())).unwrap();
+        registry.register(Box::new(trigger_bursts_total.clone())).unwrap();
+        registry.register(Box::new(frames_dropped_total.clone())).unwrap();
+        registry.register(Box::new(subscription_updates_total.clone())).unwrap();
+        registry.register(Box::new(broadcast_duration_seconds.clone())).unwrap();
+
+        Self {
+            registry,
+            connected_clients,
+            data_frames_total,
+            trigger_events_total,
+            trigger_bursts_total,
+            frames_dropped_total,
+            subscription_updates_total,
+            broadcast_duration_seconds,
+        }
+    }
+
+    /// text_encode 当前的指标快照，供 `/metrics` 路由直接返回
+    pub fn encode(&self) -> String {
+        use prometheus::Encoder;
+        let encoder = prometheus::TextEncoder::new();
+        let metric_families = self.registry.gather();
+        let mut buf = Vec::new();
+        encoder.encode(&metric_families, &mut buf).unwrap_or_default();
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+impl Default for WsMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 struct ClientConnection {
-    sender: mpsc::UnboundedSender<Message>,
+    sender: mpsc::Sender<Message>,
     subscriptions: ClientSubscriptions,
+    encoding: ClientEncoding,
+    /// 发送队列已满时被丢弃的帧数，定期随 "lag" 通知推送给客户端后清零
+    dropped_frames: AtomicU64,
+}
+
+/// 客户端协商选定的负载编码：默认 JSON 文本帧，握手时的 `?encoding=msgpack` query 参数或
+/// 之后发送的 `set_encoding` 消息都可以切换为 MessagePack 二进制帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientEncoding {
+    Json,
+    MsgPack,
+}
+
+impl Default for ClientEncoding {
+    fn default() -> Self {
+        ClientEncoding::Json
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -33,6 +139,8 @@ struct ClientSubscriptions {
     trigger_bursts: bool,        // 是否订阅触发批次完成事件
     continuous_only: bool,       // 仅订阅连续数据
     trigger_only: bool,          // 仅订阅触发数据
+    #[serde(default)]
+    filters: Vec<Condition>,     // 细粒度过滤条件（逻辑与），为空时不过滤
 }
 
 impl Default for ClientSubscriptions {
@@ -43,16 +151,119 @@ impl Default for ClientSubscriptions {
             trigger_bursts: true,    // 默认订阅触发批次完成事件
             continuous_only: false,  // 默认不限制数据类型
             trigger_only: false,
+            filters: Vec::new(),
         }
     }
 }
 
+/// 比较运算符，驱动 `subscribe` 消息里 `filters` 字段的条件求值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Operator {
+    Eq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Contains,
+    Exists,
+}
+
+/// 一条过滤条件：`key` 对应出站负载里的字段名（如 channel、sample_rate、quality、
+/// total_samples、trigger_channel），`operand` 为比较值（`Exists` 不需要）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct Condition {
+    key: String,
+    op: Operator,
+    #[serde(default)]
+    operand: serde_json::Value,
+}
+
+/// 从握手请求里提取 bearer token：优先读 `Authorization: Bearer <token>` 头，否则读
+/// query string 里的 `access_token` 参数
+fn extract_token(req: &HandshakeRequest) -> Option<String> {
+    if let Some(auth) = req.headers().get("authorization") {
+        if let Ok(s) = auth.to_str() {
+            if let Some(token) = s.strip_prefix("Bearer ") {
+                return Some(token.to_string());
+            }
+        }
+    }
+
+    query_param(req, "access_token")
+}
+
+/// 从握手请求的 query string 里取一个参数值
+fn query_param(req: &HandshakeRequest, name: &str) -> Option<String> {
+    req.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| {
+            pair.split_once('=').and_then(|(k, v)| {
+                if k == name {
+                    Some(v.to_string())
+                } else {
+                    None
+                }
+            })
+        })
+    })
+}
+
+/// 握手阶段的 `?encoding=msgpack` query 参数：让客户端从第一帧起就用 MessagePack，
+/// 不必先收一条 JSON 欢迎消息再发 `set_encoding` 切换。缺省或无法识别的值退回 JSON。
+fn extract_initial_encoding(req: &HandshakeRequest) -> ClientEncoding {
+    match query_param(req, "encoding").as_deref() {
+        Some("msgpack") => ClientEncoding::MsgPack,
+        _ => ClientEncoding::Json,
+    }
+}
+
+/// 依次求值每个条件（逻辑与，遇到第一个 false 即短路），针对已经构建好的出站 JSON 负载
+fn evaluate_filters(filters: &[Condition], payload: &serde_json::Value) -> bool {
+    filters.iter().all(|cond| evaluate_condition(cond, payload))
+}
+
+fn evaluate_condition(cond: &Condition, payload: &serde_json::Value) -> bool {
+    let value = payload.get(&cond.key);
+
+    if cond.op == Operator::Exists {
+        return value.is_some();
+    }
+
+    let value = match value {
+        Some(v) => v,
+        None => return false,
+    };
+
+    match cond.op {
+        Operator::Eq => value == &cond.operand,
+        Operator::Contains => match value {
+            serde_json::Value::String(s) => cond.operand.as_str().map_or(false, |o| s.contains(o)),
+            serde_json::Value::Array(arr) => arr.contains(&cond.operand),
+            _ => false,
+        },
+        Operator::Lt | Operator::Lte | Operator::Gt | Operator::Gte => {
+            match (value.as_f64(), cond.operand.as_f64()) {
+                (Some(v), Some(o)) => match cond.op {
+                    Operator::Lt => v < o,
+                    Operator::Lte => v <= o,
+                    Operator::Gt => v > o,
+                    Operator::Gte => v >= o,
+                    _ => false,
+                },
+                _ => false,
+            }
+        }
+        Operator::Exists => unreachable!(),
+    }
+}
+
 impl WebSocketServer {
     pub fn new(
-        config: WebSocketConfig, 
+        config: WebSocketConfig,
         data_receiver: broadcast::Receiver<ProcessedData>,
         trigger_receiver: broadcast::Receiver<TriggerEvent>,
         trigger_burst_complete_receiver: broadcast::Receiver<TriggerBurst>,
+        metrics: RuntimeMetrics,
     ) -> Self {
         let clients = Arc::new(RwLock::new(HashMap::new()));
         let (tx, rx) = watch::channel(0usize);
@@ -64,78 +275,219 @@ impl WebSocketServer {
             trigger_burst_complete_receiver,
             client_count_rx: rx,
             client_count_tx: tx,
+            metrics,
+            ws_metrics: WsMetrics::new(),
         }
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    /// `shutdown` 收到 `true` 时停止接受新连接、停掉各广播 task，并在返回前
+    /// 给所有在线客户端发送 Close 帧后清空连接表
+    pub async fn run(&mut self, shutdown: watch::Receiver<bool>) -> Result<()> {
         let addr = format!("{}:{}", self.config.host, self.config.port);
         let listener = TcpListener::bind(&addr).await?;
         info!("WebSocket server listening on {}", addr);
 
         // 数据广播 task
         let clients_clone = Arc::clone(&self.clients);
+        let metrics_clone = self.metrics.clone();
+        let client_count_tx_clone = self.client_count_tx.clone();
+        let ws_metrics_clone = self.ws_metrics.clone();
         let mut data_rx = self.data_receiver.resubscribe();
+        let mut shutdown_data = shutdown.clone();
         tokio::spawn(async move {
-            while let Ok(data) = data_rx.recv().await {
-                Self::broadcast_data(&clients_clone, &data).await;
+            loop {
+                tokio::select! {
+                    res = data_rx.recv() => {
+                        match res {
+                            Ok(data) => {
+                                let sent = Self::broadcast_data(&clients_clone, &data, &client_count_tx_clone, &ws_metrics_clone).await;
+                                metrics_clone.record_ws_messages_sent(sent);
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    _ = shutdown_data.changed() => {
+                        if *shutdown_data.borrow() { break; }
+                    }
+                }
             }
         });
 
         // 触发事件广播 task
         let clients_clone2 = Arc::clone(&self.clients);
+        let metrics_clone2 = self.metrics.clone();
+        let client_count_tx_clone2 = self.client_count_tx.clone();
+        let ws_metrics_clone2 = self.ws_metrics.clone();
         let mut trigger_rx = self.trigger_receiver.resubscribe();
+        let mut shutdown_trigger = shutdown.clone();
         tokio::spawn(async move {
-            while let Ok(trigger_event) = trigger_rx.recv().await {
-                Self::broadcast_trigger_event(&clients_clone2, &trigger_event).await;
+            loop {
+                tokio::select! {
+                    res = trigger_rx.recv() => {
+                        match res {
+                            Ok(trigger_event) => {
+                                let sent = Self::broadcast_trigger_event(&clients_clone2, &trigger_event, &client_count_tx_clone2, &ws_metrics_clone2).await;
+                                metrics_clone2.record_ws_messages_sent(sent);
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    _ = shutdown_trigger.changed() => {
+                        if *shutdown_trigger.borrow() { break; }
+                    }
+                }
+            }
+        });
+
+        // 慢消费者丢帧提醒 task：定期把各客户端自上次提醒以来丢弃的帧数推送给对应客户端
+        let clients_clone_lag = Arc::clone(&self.clients);
+        let mut shutdown_lag = shutdown.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let g = clients_clone_lag.read().await;
+                        for client in g.values() {
+                            let dropped = client.dropped_frames.swap(0, Ordering::Relaxed);
+                            if dropped > 0 {
+                                let notice = serde_json::json!({
+                                    "type": "lag",
+                                    "dropped_frames": dropped
+                                });
+                                if let Ok(text) = serde_json::to_string(&notice) {
+                                    let _ = client.sender.try_send(Message::Text(text));
+                                }
+                            }
+                        }
+                    }
+                    _ = shutdown_lag.changed() => {
+                        if *shutdown_lag.borrow() { break; }
+                    }
+                }
             }
         });
 
         // 触发批次完成事件广播 task
         let clients_clone3 = Arc::clone(&self.clients);
+        let metrics_clone3 = self.metrics.clone();
+        let client_count_tx_clone3 = self.client_count_tx.clone();
+        let ws_metrics_clone3 = self.ws_metrics.clone();
         let mut burst_complete_rx = self.trigger_burst_complete_receiver.resubscribe();
+        let mut shutdown_burst = shutdown.clone();
         tokio::spawn(async move {
-            while let Ok(trigger_burst) = burst_complete_rx.recv().await {
-                Self::broadcast_trigger_burst_complete(&clients_clone3, &trigger_burst).await;
+            loop {
+                tokio::select! {
+                    res = burst_complete_rx.recv() => {
+                        match res {
+                            Ok(trigger_burst) => {
+                                let sent = Self::broadcast_trigger_burst_complete(&clients_clone3, &trigger_burst, &client_count_tx_clone3, &ws_metrics_clone3).await;
+                                metrics_clone3.record_ws_messages_sent(sent);
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    _ = shutdown_burst.changed() => {
+                        if *shutdown_burst.borrow() { break; }
+                    }
+                }
             }
         });
 
         // 接受客户端连接
+        let mut shutdown_accept = shutdown;
         loop {
-            let (stream, addr) = listener.accept().await?;
-            info!("New WebSocket connection from {}", addr);
-
-            let clients = Arc::clone(&self.clients);
-            let tx_count = self.client_count_tx.clone();
-            tokio::spawn(async move {
-                if let Err(e) = Self::handle_connection(stream, clients, tx_count).await {
-                    error!("WebSocket connection error: {}", e);
+            tokio::select! {
+                accept_res = listener.accept() => {
+                    let (stream, addr) = accept_res?;
+                    info!("New WebSocket connection from {}", addr);
+
+                    let clients = Arc::clone(&self.clients);
+                    let tx_count = self.client_count_tx.clone();
+                    let auth_token = self.config.auth_token.clone();
+                    let channel_depth = self.config.client_channel_depth;
+                    let ws_metrics = self.ws_metrics.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = Self::handle_connection(stream, clients, tx_count, auth_token, channel_depth, ws_metrics).await {
+                            error!("WebSocket connection error: {}", e);
+                        }
+                    });
+                }
+                _ = shutdown_accept.changed() => {
+                    if *shutdown_accept.borrow() {
+                        break;
+                    }
                 }
-            });
+            }
         }
+
+        info!("WebSocket server shutting down, closing client connections");
+        Self::close_all_clients(&self.clients).await;
+        Ok(())
+    }
+
+    /// 给所有在线客户端发送 Close 帧并清空连接表，供优雅关闭时调用
+    async fn close_all_clients(clients: &Arc<RwLock<HashMap<String, ClientConnection>>>) {
+        let mut g = clients.write().await;
+        for client in g.values() {
+            let _ = client.sender.try_send(Message::Close(None));
+        }
+        g.clear();
     }
 
     async fn handle_connection(
         stream: TcpStream,
         clients: Arc<RwLock<HashMap<String, ClientConnection>>>,
         client_count_tx: watch::Sender<usize>,
+        auth_token: Option<String>,
+        channel_depth: usize,
+        ws_metrics: WsMetrics,
     ) -> Result<()> {
-        let ws_stream = accept_async(stream).await?;
+        // 握手阶段校验 bearer token（query string 的 access_token 或 Authorization 头）；
+        // 未配置 auth_token 时保持开放访问。校验失败时在回调里直接返回 401，握手永远不会升级成功，
+        // 因此还没有把连接注册进 clients 表。同时顺便读一下 `?encoding=msgpack`，让客户端可以
+        // 从连接建立起就用 MessagePack，不必等收到欢迎消息后再发 set_encoding 切换。
+        let initial_encoding = Arc::new(std::sync::Mutex::new(ClientEncoding::default()));
+        let initial_encoding_cb = Arc::clone(&initial_encoding);
+        let callback = move |req: &HandshakeRequest, response: HandshakeResponse| {
+            *initial_encoding_cb.lock().unwrap() = extract_initial_encoding(req);
+            match &auth_token {
+                Some(expected) => {
+                    let provided = extract_token(req);
+                    if provided.as_deref() == Some(expected.as_str()) {
+                        Ok(response)
+                    } else {
+                        let rejection = Response::builder()
+                            .status(401)
+                            .body(Some("Unauthorized".to_string()))
+                            .unwrap();
+                        Err(rejection)
+                    }
+                }
+                None => Ok(response),
+            }
+        };
+        let ws_stream = accept_hdr_async(stream, callback).await?;
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+        let initial_encoding = *initial_encoding.lock().unwrap();
 
         let client_id = Uuid::new_v4().to_string();
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::channel(channel_depth);
 
         // 添加到连接表
         {
             let mut g = clients.write().await;
             g.insert(
                 client_id.clone(),
-                ClientConnection { 
+                ClientConnection {
                     sender: tx.clone(),
                     subscriptions: ClientSubscriptions::default(),
+                    encoding: initial_encoding,
+                    dropped_frames: AtomicU64::new(0),
                 },
             );
             let _ = client_count_tx.send(g.len());
+            ws_metrics.connected_clients.set(g.len() as i64);
         }
 
         info!("Client {} connected", client_id);
@@ -145,21 +497,24 @@ impl WebSocketServer {
             "type": "welcome",
             "client_id": client_id,
             "timestamp": chrono::Utc::now().timestamp_millis(),
+            "encoding": if initial_encoding == ClientEncoding::MsgPack { "msgpack" } else { "json" },
             "server_capabilities": {
                 "data_streaming": true,
                 "trigger_events": true,
                 "trigger_burst_complete": true,
-                "subscription_control": true
+                "subscription_control": true,
+                "encodings": ["json", "msgpack"]
             }
         });
         if let Ok(t) = serde_json::to_string(&welcome) {
-            let _ = tx.send(Message::Text(t));
+            let _ = tx.try_send(Message::Text(t));
         }
 
         // 发送任务
         let clients_for_sender = Arc::clone(&clients);
         let client_id_for_sender = client_id.clone();
         let client_count_tx_sender = client_count_tx.clone();
+        let ws_metrics_for_sender = ws_metrics.clone();
         let sender_task = tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
                 if let Err(e) = ws_sender.send(msg).await {
@@ -173,18 +528,20 @@ impl WebSocketServer {
             let mut g = clients_for_sender.write().await;
             g.remove(&client_id_for_sender);
             let _ = client_count_tx_sender.send(g.len());
+            ws_metrics_for_sender.connected_clients.set(g.len() as i64);
             info!("Client {} disconnected", client_id_for_sender);
         });
 
         // 接收任务
         let clients_for_receiver = Arc::clone(&clients);
         let client_id_for_receiver = client_id.clone();
+        let ws_metrics_for_receiver = ws_metrics.clone();
         let receiver_task = tokio::spawn(async move {
             while let Some(msg) = ws_receiver.next().await {
                 match msg {
                     Ok(Message::Text(text)) => {
                         debug!("Client {} -> {}", client_id_for_receiver, text);
-                        if let Err(e) = Self::handle_client_message(&client_id_for_receiver, &text, &clients_for_receiver).await {
+                        if let Err(e) = Self::handle_client_message(&client_id_for_receiver, &text, &clients_for_receiver, &ws_metrics_for_receiver).await {
                             warn!("handle_client_message error: {}", e);
                         }
                     }
@@ -220,6 +577,7 @@ impl WebSocketServer {
         client_id: &str,
         message: &str,
         clients: &Arc<RwLock<HashMap<String, ClientConnection>>>,
+        ws_metrics: &WsMetrics,
     ) -> Result<()> {
         // 解析客户端消息，支持订阅控制
         if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
@@ -228,6 +586,7 @@ impl WebSocketServer {
                     "subscribe" => {
                         // 处理订阅请求
                         if let Some(channels) = msg.get("channels").and_then(|v| v.as_array()) {
+                            ws_metrics.subscription_updates_total.inc();
                             let mut g = clients.write().await;
                             if let Some(client) = g.get_mut(client_id) {
                                 // 重置订阅状态
@@ -237,8 +596,17 @@ impl WebSocketServer {
                                     trigger_bursts: false,
                                     continuous_only: false,
                                     trigger_only: false,
+                                    filters: Vec::new(),
                                 };
 
+                                // 解析细粒度过滤条件（为空或缺失时不过滤，旧版订阅请求保持兼容）
+                                if let Some(filters) = msg.get("filters") {
+                                    match serde_json::from_value::<Vec<Condition>>(filters.clone()) {
+                                        Ok(parsed) => client.subscriptions.filters = parsed,
+                                        Err(e) => warn!("Client {} sent invalid filters: {}", client_id, e),
+                                    }
+                                }
+
                                 // 根据请求设置订阅
                                 for channel in channels {
                                     if let Some(channel_str) = channel.as_str() {
@@ -274,7 +642,31 @@ impl WebSocketServer {
                                     "timestamp": chrono::Utc::now().timestamp_millis()
                                 });
                                 if let Ok(text) = serde_json::to_string(&response) {
-                                    let _ = client.sender.send(Message::Text(text));
+                                    let _ = client.sender.try_send(Message::Text(text));
+                                }
+                            }
+                        }
+                    }
+                    "set_encoding" => {
+                        // 切换客户端的数据/触发批次负载编码（json 或 msgpack）
+                        if let Some(encoding_str) = msg.get("encoding").and_then(|v| v.as_str()) {
+                            let mut g = clients.write().await;
+                            if let Some(client) = g.get_mut(client_id) {
+                                client.encoding = match encoding_str {
+                                    "msgpack" => ClientEncoding::MsgPack,
+                                    _ => ClientEncoding::Json,
+                                };
+
+                                info!("Client {} set encoding to {}", client_id, encoding_str);
+
+                                let response = serde_json::json!({
+                                    "type": "encoding_updated",
+                                    "client_id": client_id,
+                                    "encoding": encoding_str,
+                                    "timestamp": chrono::Utc::now().timestamp_millis()
+                                });
+                                if let Ok(text) = serde_json::to_string(&response) {
+                                    let _ = client.sender.try_send(Message::Text(text));
                                 }
                             }
                         }
@@ -288,7 +680,7 @@ impl WebSocketServer {
                                 "timestamp": chrono::Utc::now().timestamp_millis()
                             });
                             if let Ok(text) = serde_json::to_string(&pong) {
-                                let _ = client.sender.send(Message::Text(text));
+                                let _ = client.sender.try_send(Message::Text(text));
                             }
                         }
                     }
@@ -304,7 +696,12 @@ impl WebSocketServer {
     async fn broadcast_data(
         clients: &Arc<RwLock<HashMap<String, ClientConnection>>>,
         data: &ProcessedData,
-    ) {
+        client_count_tx: &watch::Sender<usize>,
+        ws_metrics: &WsMetrics,
+    ) -> u64 {
+        let _timer = ws_metrics.broadcast_duration_seconds.start_timer();
+        ws_metrics.data_frames_total.inc();
+        let mut sent = 0u64;
         let payload = serde_json::json!({
             "type": "data",
             "timestamp": data.timestamp,
@@ -317,9 +714,10 @@ impl WebSocketServer {
         });
 
         if let Ok(text) = serde_json::to_string(&payload) {
+            let mut msgpack: Option<Vec<u8>> = None;
             let g = clients.read().await;
             let mut drop_ids: Vec<String> = Vec::new();
-            
+
             for (id, client) in g.iter() {
                 // 检查客户端是否订阅了数据流
                 if !client.subscriptions.data_stream {
@@ -336,20 +734,48 @@ impl WebSocketServer {
                     }
                 };
 
-                if should_send {
-                    if client.sender.send(Message::Text(text.clone())).is_err() {
-                        drop_ids.push(id.clone());
+                if !should_send {
+                    continue;
+                }
+
+                if !evaluate_filters(&client.subscriptions.filters, &payload) {
+                    continue;
+                }
+
+                let msg = match client.encoding {
+                    ClientEncoding::Json => Message::Text(text.clone()),
+                    ClientEncoding::MsgPack => {
+                        let bytes = msgpack.get_or_insert_with(|| {
+                            rmp_serde::to_vec_named(&payload).unwrap_or_default()
+                        });
+                        Message::Binary(bytes.clone())
                     }
+                };
+
+                match client.sender.try_send(msg) {
+                    Ok(()) => sent += 1,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        client.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                        ws_metrics.frames_dropped_total.inc();
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => drop_ids.push(id.clone()),
                 }
             }
-            drop(drop_ids);
+
+            Self::evict_clients(clients, drop_ids, client_count_tx, ws_metrics).await;
         }
+        sent
     }
 
     async fn broadcast_trigger_event(
         clients: &Arc<RwLock<HashMap<String, ClientConnection>>>,
         trigger_event: &TriggerEvent,
-    ) {
+        client_count_tx: &watch::Sender<usize>,
+        ws_metrics: &WsMetrics,
+    ) -> u64 {
+        let _timer = ws_metrics.broadcast_duration_seconds.start_timer();
+        ws_metrics.trigger_events_total.inc();
+        let mut sent = 0u64;
         let payload = serde_json::json!({
             "type": "trigger_event",
             "timestamp": trigger_event.timestamp,
@@ -362,29 +788,59 @@ impl WebSocketServer {
         if let Ok(text) = serde_json::to_string(&payload) {
             let g = clients.read().await;
             let mut drop_ids: Vec<String> = Vec::new();
-            
+
             for (id, client) in g.iter() {
-                // 只发送给订阅了触发事件的客户端
-                if client.subscriptions.trigger_events {
-                    if client.sender.send(Message::Text(text.clone())).is_err() {
-                        drop_ids.push(id.clone());
+                // 只发送给订阅了触发事件且满足过滤条件的客户端
+                if client.subscriptions.trigger_events && evaluate_filters(&client.subscriptions.filters, &payload) {
+                    match client.sender.try_send(Message::Text(text.clone())) {
+                        Ok(()) => sent += 1,
+                        Err(mpsc::error::TrySendError::Full(_)) => {
+                            client.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                            ws_metrics.frames_dropped_total.inc();
+                        }
+                        Err(mpsc::error::TrySendError::Closed(_)) => drop_ids.push(id.clone()),
                     }
                 }
             }
-            drop(drop_ids);
+
+            Self::evict_clients(clients, drop_ids, client_count_tx, ws_metrics).await;
         }
 
         info!(
-            "Broadcasted trigger event to clients: ts={}, ch={}", 
+            "Broadcasted trigger event to clients: ts={}, ch={}",
             trigger_event.timestamp, trigger_event.channel
         );
+        sent
+    }
+
+    /// 从连接表中移除已失效（发送端已关闭）的客户端，并更新在线计数
+    async fn evict_clients(
+        clients: &Arc<RwLock<HashMap<String, ClientConnection>>>,
+        drop_ids: Vec<String>,
+        client_count_tx: &watch::Sender<usize>,
+        ws_metrics: &WsMetrics,
+    ) {
+        if drop_ids.is_empty() {
+            return;
+        }
+        let mut g = clients.write().await;
+        for id in &drop_ids {
+            g.remove(id);
+        }
+        let _ = client_count_tx.send(g.len());
+        ws_metrics.connected_clients.set(g.len() as i64);
     }
 
     /// 广播触发批次完成事件
     async fn broadcast_trigger_burst_complete(
         clients: &Arc<RwLock<HashMap<String, ClientConnection>>>,
         trigger_burst: &TriggerBurst,
-    ) {
+        client_count_tx: &watch::Sender<usize>,
+        ws_metrics: &WsMetrics,
+    ) -> u64 {
+        let _timer = ws_metrics.broadcast_duration_seconds.start_timer();
+        ws_metrics.trigger_bursts_total.inc();
+        let mut sent = 0u64;
         let payload = serde_json::json!({
             "type": "trigger_burst_complete",
             "burst_id": trigger_burst.burst_id,
@@ -402,31 +858,55 @@ impl WebSocketServer {
             "created_at": trigger_burst.created_at,
             "preview_samples": Self::extract_preview_samples(trigger_burst),
             "channel_stats": trigger_burst.quality_summary.channel_stats,
-            "voltage_range": trigger_burst.quality_summary.voltage_range,
+            "voltage_range": trigger_burst.quality_summary.value_range,
             "event_time": chrono::Utc::now().timestamp_millis()
         });
 
         if let Ok(text) = serde_json::to_string(&payload) {
+            let mut msgpack: Option<Vec<u8>> = None;
             let g = clients.read().await;
             let mut drop_ids: Vec<String> = Vec::new();
-            
+
             for (id, client) in g.iter() {
                 // 只发送给订阅了触发批次完成事件的客户端
-                if client.subscriptions.trigger_bursts {
-                    if client.sender.send(Message::Text(text.clone())).is_err() {
-                        drop_ids.push(id.clone());
+                if !client.subscriptions.trigger_bursts {
+                    continue;
+                }
+
+                if !evaluate_filters(&client.subscriptions.filters, &payload) {
+                    continue;
+                }
+
+                let msg = match client.encoding {
+                    ClientEncoding::Json => Message::Text(text.clone()),
+                    ClientEncoding::MsgPack => {
+                        let bytes = msgpack.get_or_insert_with(|| {
+                            rmp_serde::to_vec_named(&payload).unwrap_or_default()
+                        });
+                        Message::Binary(bytes.clone())
                     }
+                };
+
+                match client.sender.try_send(msg) {
+                    Ok(()) => sent += 1,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        client.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                        ws_metrics.frames_dropped_total.inc();
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => drop_ids.push(id.clone()),
                 }
             }
-            drop(drop_ids);
+
+            Self::evict_clients(clients, drop_ids, client_count_tx, ws_metrics).await;
         }
 
         info!(
-            "Broadcasted trigger burst complete: id={}, samples={}, packets={}", 
+            "Broadcasted trigger burst complete: id={}, samples={}, packets={}",
             trigger_burst.burst_id,
             trigger_burst.total_samples,
             trigger_burst.data_packets.len()
         );
+        sent
     }
 
     /// 计算触发批次持续时间